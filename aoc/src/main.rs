@@ -1,11 +1,26 @@
-use aoc2019;
-
 fn main() {
+    //aoc2019::day1::run();
     //aoc2019::day2::run();
     //aoc2019::day3::part1();
     //aoc2019::day3::part2();
-    //aoc2019::day4::part1();
+    //aoc2019::day3::draw();
+    //aoc2019::day4::run();
     //aoc2019::day5::run();
     //aoc2019::day6::run();
+    //aoc2019::day9::run();
+    //aoc2019::day11::run();
+    //aoc2019::day12::run();
+    //aoc2019::day13::run();
+    //aoc2019::day13::watch_autopilot();
+    //aoc2019::day14::run();
+    //aoc2019::day15::run();
+    //aoc2019::day16::run();
+    //aoc2019::day17::run();
+    //aoc2019::day18::run();
+    //aoc2019::day19::run();
+    //aoc2019::day20::run();
+    //aoc2019::day21::run();
+    //aoc2019::day22::run();
+    //aoc2019::day23::run();
     aoc2019::day10::run();
 }
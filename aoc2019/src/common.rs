@@ -3,28 +3,55 @@ use std::str::FromStr;
 use std::fs;
 use std::path::PathBuf;
 use std::env;
-use std::fmt::Debug;
+use std::fmt::{self,Debug};
 
 const DATA_DIR_ENV_VAR: &str = "AOC2019_DATA";
-pub const F32_RAD_TO_DEG: f32 = 180f32 / std::f32::consts::PI;
 
 #[derive(Debug)]
 pub enum Error {
     DataDirEnvVarNotSet,
     DayDirNotRelative,
     DataFileNotRelative,
-    IoError(io::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::DataDirEnvVarNotSet => write!(f, "{} isn't set", DATA_DIR_ENV_VAR),
+            Error::DayDirNotRelative => write!(f, "Day directory isn't relative"),
+            Error::DataFileNotRelative => write!(f, "Data file isn't relative"),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Error {
-        Error::IoError(e)
+        Error::Io(e)
     }
 }
 
 /// Use env-var for root
+/// (tests fall back to `data/day` at the top of the repo)
 fn data_root() -> Result<PathBuf,Error> {
     match env::var(DATA_DIR_ENV_VAR) {
+        Err(_) if cfg!(test) => {
+            let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+            path.pop();
+            path.push("data");
+            path.push("day");
+            Ok(path)
+        },
         Err(_) => Err(Error::DataDirEnvVarNotSet),
         Ok(val) => Ok(PathBuf::from_str(&val).unwrap())
     }
@@ -49,16 +76,11 @@ pub fn path_to_data_file(day: &str, file: &str)
     }
 }
 
-/// Get path to input file for a given day
-fn path_to_input(day: &str) -> Result<PathBuf,Error> {
-    path_to_data_file(day, "input")
-}
-
 pub fn data_lines(path: &PathBuf)
 -> Box<dyn Iterator<Item=Result<String, impl Into<Error>>>>
 {
     let file = fs::File::open(path).expect("Couldn't open file");
-    let mut br = io::BufReader::new(file);
+    let br = io::BufReader::new(file);
     Box::new(br.lines())
 }
 
@@ -67,7 +89,10 @@ pub fn data_file_to_string(day: &str, file: &str) -> Result<String, Error> {
     let mut s = String::new();
     for line in data_lines(&path) {
         match line {
-            Ok(l) => s.push_str(&l),
+            Ok(l) => {
+                s.push_str(&l);
+                s.push('\n');
+            },
             Err(e) => return Err(e.into()),
         };
     }
@@ -89,3 +114,76 @@ pub fn gcd(mut m: isize, mut n: isize) -> isize {
         n.abs()
     }
 }
+
+
+/// Least Common Multiple, or None if it doesn't fit
+pub fn lcm(m: isize, n: isize) -> Option<isize> {
+    (m / gcd(m, n)).checked_mul(n)?.checked_abs()
+}
+
+/// Arithmetic modulo some number, worked in 128 bits so that products
+/// of 64-bit values don't overflow on the way. Results are always in
+/// `0..modulus`, and anything that would overflow anyway gives None.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Modulo {
+    modulus: i128,
+}
+
+impl Modulo {
+    /// None for a modulus of 0.
+    pub fn new(modulus: u64) -> Option<Self> {
+        if modulus == 0 { None } else { Some(Modulo { modulus: i128::from(modulus) }) }
+    }
+
+    pub fn modulus(&self) -> i128 {
+        self.modulus
+    }
+
+    pub fn reduce(&self, a: i128) -> i128 {
+        a.rem_euclid(self.modulus)
+    }
+
+    pub fn add(&self, a: i128, b: i128) -> Option<i128> {
+        Some(self.reduce(self.reduce(a).checked_add(self.reduce(b))?))
+    }
+
+    pub fn sub(&self, a: i128, b: i128) -> Option<i128> {
+        Some(self.reduce(self.reduce(a).checked_sub(self.reduce(b))?))
+    }
+
+    pub fn mul(&self, a: i128, b: i128) -> Option<i128> {
+        Some(self.reduce(self.reduce(a).checked_mul(self.reduce(b))?))
+    }
+
+    /// The `x` with `a * x == 1`, if `a` and the modulus are coprime.
+    pub fn inverse(&self, a: i128) -> Option<i128> {
+        // Extended Euclid, tracking only the coefficient of `a`
+        let (mut r, mut new_r) = (self.modulus, self.reduce(a));
+        let (mut t, mut new_t) = (0i128, 1i128);
+        while new_r != 0 {
+            let q = r / new_r;
+            (r, new_r) = (new_r, r - q * new_r);
+            (t, new_t) = (new_t, t.checked_sub(q.checked_mul(new_t)?)?);
+        }
+        if r == 1 { Some(self.reduce(t)) } else { None }
+    }
+}
+
+#[test]
+fn test_modulo() {
+    assert_eq!(Modulo::new(0), None);
+    let m = Modulo::new(10).unwrap();
+    assert_eq!(m.reduce(-3), 7);
+    assert_eq!(m.add(7, 8), Some(5));
+    assert_eq!(m.sub(3, 8), Some(5));
+    assert_eq!(m.mul(-3, 4), Some(8));
+    assert_eq!(m.inverse(3), Some(7));
+    assert_eq!(m.inverse(4), None);
+
+    // Products of big values still work, until they can't fit at all
+    let big = Modulo::new(119315717514047).unwrap();
+    let x = 101741582076661;
+    assert_eq!(big.mul(big.inverse(x).unwrap(), x), Some(1));
+    let huge = Modulo::new(u64::MAX).unwrap();
+    assert_eq!(huge.mul(-1, -1), None);
+}
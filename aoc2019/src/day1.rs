@@ -1,27 +1,99 @@
 use crate::common::*;
 
-fn calc_fuel(mass: isize) -> isize {
-    let fuel = mass as isize / 3 - 2;
-    if fuel < 1 {
-        0
+/// Problems with the list of module masses.
+#[derive(Debug,PartialEq)]
+pub enum FuelError {
+    /// A line (numbered from 1) that isn't a mass
+    BadMass(usize, String),
+    /// The total fuel doesn't fit in a u64
+    Overflow,
+}
+
+/// Fuel for a module's mass alone (part 1).
+pub fn fuel(mass: u64) -> u64 {
+    (mass / 3).saturating_sub(2)
+}
+
+/// Fuel for a module's mass, plus fuel for that fuel, and so on
+/// until it needs no more (part 2).
+pub fn fuel_with_fuel(mass: u64) -> u64 {
+    let mut total = 0;
+    let mut extra = fuel(mass);
+    while extra > 0 {
+        // Each step is a third of the last, so this can't overflow
+        total += extra;
+        extra = fuel(extra);
     }
-    else {
-        fuel + calc_fuel(fuel)
+    total
+}
+
+#[test]
+fn test_fuel() {
+    let tests = [
+        (12, 2, 2),
+        (14, 2, 2),
+        (1969, 654, 966),
+        (100756, 33583, 50346),
+        (0, 0, 0),
+        (8, 0, 0),
+    ];
+    for (mass, simple, recursive) in tests.iter() {
+        assert_eq!(fuel(*mass), *simple);
+        assert_eq!(fuel_with_fuel(*mass), *recursive);
     }
+    // Huge masses don't overflow
+    assert_eq!(fuel(u64::MAX), u64::MAX / 3 - 2);
+    assert!(fuel_with_fuel(u64::MAX) < u64::MAX / 2);
+}
+
+/// Add up the fuel for all the modules.
+pub fn total(masses: &[u64], fuel_for: fn(u64) -> u64) -> Result<u64, FuelError> {
+    masses
+        .iter()
+        .try_fold(0u64, |acc, m| acc.checked_add(fuel_for(*m)))
+        .ok_or(FuelError::Overflow)
+}
+
+#[test]
+fn test_total() {
+    let masses = [12, 14, 1969, 100756];
+    assert_eq!(total(&masses, fuel), Ok(2 + 2 + 654 + 33583));
+    assert_eq!(total(&masses, fuel_with_fuel), Ok(2 + 2 + 966 + 50346));
+    assert_eq!(total(&[u64::MAX; 4], fuel), Err(FuelError::Overflow));
+}
+
+/// Read one mass per line, ignoring blank lines.
+pub fn parse_masses(source: &str) -> Result<Vec<u64>, FuelError> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(n, line)| line
+             .parse::<u64>()
+             .map_err(|_| FuelError::BadMass(n, String::from(line))))
+        .collect()
+}
+
+#[test]
+fn test_parse_masses() {
+    assert_eq!(parse_masses("12\n\n 14 \n"), Ok(vec![12, 14]));
+    assert_eq!(parse_masses("12\n\nfourteen\n-3"),
+               Err(FuelError::BadMass(3, String::from("fourteen"))));
+    assert_eq!(parse_masses("12\n-3"),
+               Err(FuelError::BadMass(2, String::from("-3"))));
 }
 
 pub fn run() {
     let source = data_file_to_string("1", "input").unwrap();
-    let lines = 
-        source
-            .split("\n")
-            .map(|line| line.trim())
-            .filter(|line| line.len() > 0);
-    let result: usize = 
-        lines
-            .map(|m| m.parse::<usize>().unwrap())
-            //.map(|m| m / 3 - 2)
-            .map(|m| calc_fuel(m as isize) as usize)
-            .sum();
-    println!("{}", result);
+    let masses = match parse_masses(&source) {
+        Ok(masses) => masses,
+        Err(e) => panic!("{:?}", e),
+    };
+    for fuel_for in [fuel, fuel_with_fuel] {
+        match total(&masses, fuel_for) {
+            Ok(fuel) => println!("{}", fuel),
+            Err(e) => println!("{:?}", e),
+        }
+    }
 }
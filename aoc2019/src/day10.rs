@@ -1,74 +1,96 @@
 use std::convert::From;
 use std::path::PathBuf;
-use std::collections::BTreeMap;
-use std::cmp::PartialEq;
+use std::collections::{HashMap,HashSet};
+use std::cmp::Ordering;
+use std::cell::OnceCell;
+use std::thread;
 use crate::common::*;
 
-// Angle multiplier for accuracy
-const ANGLE_MULT: f32 = 100.0;
-// Angle*ANGLE_MULT (from upright) and square of distance
-type Dist2 = usize;
-type AngleM = usize;
-type Polar = (AngleM, Dist2);
+// Direction to another asteroid, reduced by the gcd of its components
+// so that everything along the same line of sight shares it.
+type Direction = (isize, isize);
 type AsteroidIdx = usize;
 
 /*
  * ASTEROID
  */
 
-struct Asteroid {
-    x: usize,
-    y: usize,
-    lines_of_sight: BTreeMap<AngleM, BTreeMap<Dist2,AsteroidIdx>>,
-}
-
-/// If asteroids are at the exact same position, then they're the
-/// same asteroid, for the purposes of this
-impl PartialEq for Asteroid {
-    fn eq(&self, other: &Self) -> bool {
-        self.x == other.x && self.y == other.y
-    }
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Asteroid {
+    pub x: usize,
+    pub y: usize,
 }
 
 impl Asteroid {
     fn new(x: usize, y: usize) -> Asteroid {
-        Asteroid { x, y, lines_of_sight: BTreeMap::new() }
+        Asteroid { x, y }
     }
 
-    /// Calculate Polar of an asteroid relative to this one.
-    fn polar(&self, other: &Asteroid) -> Polar {
-        let px = other.x as isize - self.x as isize;
-        let py = other.y as isize - self.y as isize;
-        let dist2 = (px.pow(2) + py.pow(2)) as Dist2;
-        let anglem = f32::atan2(py as f32, px as f32)
-                    * 180f32 / std::f32::consts::PI;
-        let anglem = (((anglem + 450f32) % 360f32) * ANGLE_MULT) as AngleM;
-        (anglem, dist2)
+    /// Direction of an asteroid relative to this one, and how many
+    /// steps along that direction it is.
+    fn direction(&self, other: &Asteroid) -> (Direction, isize) {
+        let dx = other.x as isize - self.x as isize;
+        let dy = other.y as isize - self.y as isize;
+        let steps = gcd(dx, dy);
+        ((dx / steps, dy / steps), steps)
     }
 }
 
+/// Order directions clockwise starting from straight up
+/// (y goes down the map, so "up" is negative y).
+fn clockwise(a: &Direction, b: &Direction) -> Ordering {
+    // Right-hand half (including up) comes before the left-hand half
+    let half = |d: &Direction| if d.0 > 0 || (d.0 == 0 && d.1 < 0) { 0 } else { 1 };
+    half(a).cmp(&half(b))
+        .then_with(|| (b.0 * a.1).cmp(&(a.0 * b.1)))
+}
+
 
 /*
  * BELT
  */
 
-struct Belt {
+pub struct Belt {
     asteroids: Vec<Asteroid>,
+    // Count of visible asteroids from each asteroid, worked out on demand
+    visibility: OnceCell<Vec<usize>>,
 }
 
 impl From<&PathBuf> for Belt {
     /// "Convert" a file-path into a Belt with all the
     /// Asteroids, if it doesn't crash for some I/O reason.
     fn from(path: &PathBuf) -> Self {
-        // Read lines from the input and convert them to new Asteroids
-        let mut asteroids:Vec<Asteroid> = Vec::new();
-        for (y, line) in data_lines(path).enumerate() {
-            let mut line = match line {
+        let lines = data_lines(path)
+            .map(|line| match line {
                 Ok(s) => s,
                 Err(e) => panic!("{:?}", e.into()),
-            };
-            line = String::from(line.trim());
-            if line.len() == 0 {
+            });
+        Belt::from_lines(lines)
+    }
+}
+
+impl From<&str> for Belt {
+    /// Convert a map drawn in a string into a Belt.
+    fn from(map: &str) -> Self {
+        Belt::from_lines(map.lines().map(String::from))
+    }
+}
+
+impl From<Vec<Asteroid>> for Belt {
+    fn from(asteroids: Vec<Asteroid>) -> Self {
+        Belt { asteroids, visibility: OnceCell::new() }
+    }
+}
+
+
+impl Belt {
+
+    /// Read lines of the map and convert them to new Asteroids
+    fn from_lines(lines: impl Iterator<Item=String>) -> Self {
+        let mut asteroids:Vec<Asteroid> = Vec::new();
+        for (y, line) in lines.enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
                 continue;
             }
             line
@@ -78,55 +100,321 @@ impl From<&PathBuf> for Belt {
                 .map(|(x, _)| x)
                 .for_each(|x| asteroids.push(Asteroid::new(x, y)));
         }
+        Belt::from(asteroids)
+    }
 
-        // Initialize all the lines of sight for every asteroid
-        let mut polar;
-        for i_pov in 0..asteroids.len() {
-            polar = Belt::calc_lines_of_sight(i_pov, &asteroids);
-            asteroids[i_pov].lines_of_sight = polar;
+    /// Count the distinct lines of sight from a pov. Only the
+    /// directions are kept, so this needs O(n) memory.
+    /// An asteroid sitting right on the pov is ignored.
+    fn count_lines_of_sight(pov: &Asteroid, asteroids: &[Asteroid],
+                            seen: &mut HashSet<Direction>) -> usize {
+        seen.clear();
+        for asteroid in asteroids {
+            if asteroid != pov {
+                seen.insert(pov.direction(asteroid).0);
+            }
         }
-        Belt { asteroids }
+        seen.len()
     }
-}
 
+    /// Count lines of sight for every asteroid, split across threads.
+    fn calc_visibility(asteroids: &[Asteroid]) -> Vec<usize> {
+        let threads = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let chunk_size = asteroids.len().div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            let workers = asteroids
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || {
+                    let mut seen = HashSet::new();
+                    chunk
+                        .iter()
+                        .map(|pov| Belt::count_lines_of_sight(pov, asteroids, &mut seen))
+                        .collect::<Vec<usize>>()
+                }))
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap())
+                .collect()
+        })
+    }
 
-impl Belt {
-
-    /// For every line of sight, map distance to asteroid (index)
-    fn calc_lines_of_sight(i_pov: AsteroidIdx, asteroids: &Vec<Asteroid>) 
-    -> BTreeMap<AngleM, BTreeMap<Dist2,AsteroidIdx>> {
-        let pov = &asteroids[i_pov];
-        let mut polars: BTreeMap<AngleM,BTreeMap<Dist2,AsteroidIdx>> = 
-            BTreeMap::new();
-        let mut polar;
+    /// For every line of sight from a pov, list the asteroids
+    /// (nearest first), with the lines ordered clockwise from up.
+    fn lines_of_sight(pov: &Asteroid, asteroids: &[Asteroid])
+    -> Vec<Vec<AsteroidIdx>> {
+        let mut lines: HashMap<Direction, Vec<(isize, AsteroidIdx)>> =
+            HashMap::new();
         for (i, asteroid) in asteroids.iter().enumerate() {
-            if i != i_pov {  // ignore pov asteroid
-                polar = pov.polar(asteroid);
-                polars
-                    .entry(polar.0)
-                    .or_insert(BTreeMap::new())
-                    .insert(polar.1, i);
+            if asteroid != pov {
+                let (direction, steps) = pov.direction(asteroid);
+                lines.entry(direction).or_default().push((steps, i));
             }
         }
-        polars
+        let mut lines = lines.into_iter().collect::<Vec<_>>();
+        lines.sort_by(|a, b| clockwise(&a.0, &b.0));
+        lines
+            .into_iter()
+            .map(|(_, mut line)| {
+                line.sort();
+                line.into_iter().map(|(_, i)| i).collect()
+            })
+            .collect()
     }
 
-    /// Find the asteroid with best visibility of other asteroids.
-    /// Whichever has most lines of sight wins.
-    fn best_visibility(&self) -> &Asteroid {
+    pub fn asteroids(&self) -> &[Asteroid] {
+        &self.asteroids
+    }
+
+    /// How many other asteroids each asteroid can see, in the same
+    /// order as `asteroids()`.
+    pub fn visibility(&self) -> &[usize] {
+        self.visibility.get_or_init(|| Belt::calc_visibility(&self.asteroids))
+    }
+
+    /// Find the asteroid with best visibility of other asteroids,
+    /// and how many it can see. Whichever has most lines of sight wins.
+    pub fn best_visibility(&self) -> Option<(&Asteroid, usize)> {
         self.asteroids
             .iter()
-            .fold(&self.asteroids[0],
-                  |acc, i|
-                  if i.lines_of_sight.len() > acc.lines_of_sight.len() {
-                      i
-                  }
-                  else {
-                      acc
+            .zip(self.visibility().iter().cloned())
+            .fold(None, |acc: Option<(&Asteroid, usize)>, i|
+                  match acc {
+                      Some(best) if best.1 >= i.1 => Some(best),
+                      _ => Some(i),
                   }
                 )
     }
 
+    /// Sweep the laser from a station at (x, y), starting straight up
+    /// and turning clockwise, yielding asteroids in the order they get
+    /// vaporized. The station doesn't need to be on an asteroid.
+    pub fn vaporization_order(&self, x: usize, y: usize) -> Vaporizer<'_> {
+        let pov = Asteroid::new(x, y);
+        let lines_of_sight = Belt::lines_of_sight(&pov, &self.asteroids);
+        Vaporizer { belt: self, lines_of_sight, rotation: 1, line: 0 }
+    }
+
+    /// The nth (counting from 1) asteroid vaporized from a station
+    /// at (x, y), if there are that many.
+    pub fn nth_vaporized(&self, x: usize, y: usize, n: usize)
+    -> Option<Vaporized> {
+        self.vaporization_order(x, y).nth(n.checked_sub(1)?)
+    }
+
+}
+
+#[test]
+fn test_best_visibility() {
+    let tests = [
+        ("example_1", (5, 8), 33),
+        ("example_2", (1, 2), 35),
+        ("example_3", (6, 3), 41),
+        ("example_4", (11, 13), 210),
+    ];
+    for (file, position, visible) in tests.iter() {
+        let belt = Belt::from(&path_to_data_file("10", file).unwrap());
+        let (pov, actual) = belt.best_visibility().unwrap();
+        assert_eq!(((pov.x, pov.y), actual), (*position, *visible));
+    }
+
+    // Every asteroid on a full grid sees its neighbours, plus
+    // anything else along a line whose steps are coprime
+    let belt = Belt::from("###\n###\n###");
+    assert_eq!(belt.visibility(), &[5, 7, 5, 7, 8, 7, 5, 7, 5]);
+    assert!(Belt::from("...").best_visibility().is_none());
+}
+
+
+/*
+ * VAPORIZER
+ */
+
+/// An asteroid zapped by the laser.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Vaporized {
+    pub x: usize,
+    pub y: usize,
+    /// Which turn of the laser got it (the first turn is 1)
+    pub rotation: usize,
+}
+
+/// Iterator over asteroids in the order the laser zaps them.
+pub struct Vaporizer<'a> {
+    belt: &'a Belt,
+    // Each line of sight, ordered by angle, holds asteroids nearest first
+    lines_of_sight: Vec<Vec<AsteroidIdx>>,
+    rotation: usize,
+    line: usize,
+}
+
+impl Iterator for Vaporizer<'_> {
+    type Item = Vaporized;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.line == self.lines_of_sight.len() {
+                // Finished a turn; stop if nothing's left for the next one
+                self.line = 0;
+                self.rotation += 1;
+                if self.lines_of_sight
+                    .iter()
+                    .all(|l| l.len() < self.rotation) {
+                    return None;
+                }
+            }
+            let line = &self.lines_of_sight[self.line];
+            self.line += 1;
+            if let Some(i) = line.get(self.rotation - 1) {
+                let asteroid = &self.belt.asteroids[*i];
+                return Some(Vaporized {
+                    x: asteroid.x,
+                    y: asteroid.y,
+                    rotation: self.rotation,
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vaporization_order() {
+    let path = path_to_data_file("10", "example_4").unwrap();
+    let belt = Belt::from(&path);
+    let (pov, visible) = belt.best_visibility().unwrap();
+
+    let order = belt.vaporization_order(pov.x, pov.y).collect::<Vec<_>>();
+    assert_eq!(order.len(), belt.asteroids().len() - 1);
+    let tests = [
+        (1, (11, 12)),
+        (2, (12, 1)),
+        (3, (12, 2)),
+        (10, (12, 8)),
+        (20, (16, 0)),
+        (50, (16, 9)),
+        (100, (10, 16)),
+        (199, (9, 6)),
+        (200, (8, 2)),
+        (201, (10, 9)),
+        (299, (11, 1)),
+    ];
+    for (n, expected) in tests.iter() {
+        let zapped = order[n - 1];
+        assert_eq!((zapped.x, zapped.y), *expected);
+        assert_eq!(belt.nth_vaporized(pov.x, pov.y, *n), Some(zapped));
+    }
+    assert_eq!(order[0].rotation, 1);
+    assert_eq!(order[visible - 1].rotation, 1);
+    assert_eq!(order[visible].rotation, 2);
+    assert_eq!(belt.nth_vaporized(pov.x, pov.y, 0), None);
+    assert_eq!(belt.nth_vaporized(pov.x, pov.y, 300), None);
+}
+
+#[test]
+fn test_vaporization_order_off_asteroid() {
+    let belt = Belt::from("\
+        .#....#####...#..\n\
+        ##...##.#####..##\n\
+        ##...#...#.#####.\n\
+        ..#.....X...###..\n\
+        ..#.#.....#....##");
+    let expected = [
+        (8, 1), (9, 0), (9, 1), (10, 0), (9, 2), (11, 1), (12, 1), (11, 2),
+        (15, 1), (12, 2), (13, 2), (14, 2), (15, 2), (12, 3), (16, 4),
+        (15, 4), (10, 4), (4, 4), (2, 4), (2, 3), (0, 2), (1, 2), (0, 1),
+        (1, 1), (5, 2), (1, 0), (5, 1), (6, 1), (6, 0), (7, 0), (8, 0),
+        (10, 1), (14, 0), (16, 1), (13, 3), (14, 3),
+    ];
+    let actual = belt.vaporization_order(8, 3)
+        .map(|v| (v.x, v.y))
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+}
+
+
+/*
+ * RENDERING
+ */
+
+/// What to write on each asteroid when rendering the belt.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Label {
+    /// How many other asteroids it can see
+    Visibility,
+    /// When the laser at the best station gets it
+    VaporizationOrder,
+}
+
+impl Belt {
+
+    /// Draw the map as a text grid with the best station in brackets
+    /// and every asteroid labelled. Empty space is drawn as `.`,
+    /// and the station shows an `X` when labelling vaporization order.
+    pub fn render(&self, label: Label) -> String {
+        let station = self.best_visibility().map(|(pov, _)| *pov);
+        let mut labels: HashMap<(usize, usize), String> = HashMap::new();
+        match label {
+            Label::Visibility => {
+                for (a, v) in self.asteroids.iter().zip(self.visibility()) {
+                    labels.insert((a.x, a.y), v.to_string());
+                }
+            },
+            Label::VaporizationOrder => {
+                if let Some(pov) = station {
+                    labels.insert((pov.x, pov.y), String::from("X"));
+                    for (n, v) in self.vaporization_order(pov.x, pov.y).enumerate() {
+                        labels.insert((v.x, v.y), (n + 1).to_string());
+                    }
+                }
+            },
+        }
+
+        let width = self.asteroids.iter().map(|a| a.x + 1).max().unwrap_or(0);
+        let height = self.asteroids.iter().map(|a| a.y + 1).max().unwrap_or(0);
+        let cell = labels.values().map(|l| l.len()).max().unwrap_or(1);
+        (0..height)
+            .map(|y| {
+                let row = (0..width)
+                    .map(|x| {
+                        let text = labels.get(&(x, y)).map_or(".", |l| l.as_str());
+                        if station == Some(Asteroid::new(x, y)) {
+                            format!("[{:>w$}]", text, w = cell)
+                        }
+                        else {
+                            format!(" {:>w$} ", text, w = cell)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join("");
+                String::from(row.trim_end())
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+}
+
+#[test]
+fn test_render() {
+    let belt = Belt::from(".#..#\n.....\n#####\n....#\n...##");
+    assert_eq!(
+        belt.render(Label::Visibility),
+        " .  7  .  .  7\n\
+        \x20.  .  .  .  .\n\
+        \x206  7  7  7  5\n\
+        \x20.  .  .  .  7\n\
+        \x20.  .  . [8] 7"
+    );
+    assert_eq!(
+        belt.render(Label::VaporizationOrder),
+        " .  9  .  .  2\n\
+        \x20.  .  .  .  .\n\
+        \x206  7  8  1  3\n\
+        \x20.  .  .  .  4\n\
+        \x20.  .  . [X] 5"
+    );
 }
 
 
@@ -140,29 +428,18 @@ pub fn run() {
 
     // Show how many asteroids and the one that can see the most
     println!("{}", belt.asteroids.len());
-    let pov = belt.best_visibility();
-    println!("{}, {} => {}", pov.x, pov.y, pov.lines_of_sight.len());
-
-    // iterate over iterators in each line of sight
-    // all ordered by key bcoz btreemaps
-    let mut los_iter = pov.lines_of_sight
-        .values()  // values: Dist2 => AsteroidIdx 
-        .map(|di|
-             di.values())  // each is now AsteroidIdx
-        .collect::<Vec<_>>();
-    let mut count = 0;
-    let mut andidx: usize = 0;
-    'layzur: loop {
-        for ang in &mut los_iter {
-            if let Some(a) = ang.next() {
-                andidx = *a;
-                count += 1;
-                if count == 200 {
-                    break 'layzur;
-                }
-            }
-        }
+    let (pov, visible) = match belt.best_visibility() {
+        Some(best) => best,
+        None => panic!("No asteroids in the belt"),
+    };
+    println!("{}, {} => {}", pov.x, pov.y, visible);
+
+    // Sweep the laser round until the 200th asteroid goes
+    match belt.nth_vaporized(pov.x, pov.y, 200) {
+        Some(lasteroid) => println!("RAH!!! {}, {}", lasteroid.x, lasteroid.y),
+        None => println!("Not enough asteroids to zap"),
     }
-    let lasteroid = &belt.asteroids[andidx];
-    println!("RAH!!! {}, {}", lasteroid.x, lasteroid.y);
+
+    println!("{}", belt.render(Label::Visibility));
+    println!("{}", belt.render(Label::VaporizationOrder));
 }
@@ -0,0 +1,155 @@
+use std::collections::{HashMap,HashSet};
+
+use crate::common::*;
+use crate::intcode::{Event,Intcode,Word,parse_program};
+
+type Coords = (isize, isize);
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Colour {
+    Black,
+    White,
+}
+
+impl Colour {
+    fn from_word(word: Word) -> Colour {
+        match word {
+            0 => Colour::Black,
+            1 => Colour::White,
+            _ => panic!("Unknown colour {}", word),
+        }
+    }
+
+    fn to_word(self) -> Word {
+        match self {
+            Colour::Black => 0,
+            Colour::White => 1,
+        }
+    }
+}
+
+/// Way the robot's facing, as a step on the grid (y goes down).
+type Facing = (isize, isize);
+const UP: Facing = (0, -1);
+
+/// Emergency hull painting robot. Panels it hasn't painted are black,
+/// apart from the one it starts on which may be white.
+pub struct Robot {
+    position: Coords,
+    facing: Facing,
+    panels: HashMap<Coords, Colour>,
+    painted: HashSet<Coords>,
+}
+
+impl Robot {
+    pub fn new(start: Colour) -> Self {
+        let mut panels = HashMap::new();
+        panels.insert((0, 0), start);
+        Robot { position: (0, 0), facing: UP, panels, painted: HashSet::new() }
+    }
+
+    /// Colour of the panel the robot's on.
+    pub fn camera(&self) -> Colour {
+        self.panels.get(&self.position).copied().unwrap_or(Colour::Black)
+    }
+
+    /// Paint the current panel, turn left (0) or right (1), and move
+    /// forward a panel.
+    pub fn paint(&mut self, colour: Colour, turn: Word) {
+        self.panels.insert(self.position, colour);
+        self.painted.insert(self.position);
+        let (dx, dy) = self.facing;
+        self.facing = match turn {
+            0 => (dy, -dx),
+            1 => (-dy, dx),
+            _ => panic!("Unknown turn {}", turn),
+        };
+        self.position = (self.position.0 + self.facing.0,
+                         self.position.1 + self.facing.1);
+    }
+
+    /// Let the Intcode brain drive until it halts. It's given the camera
+    /// colour whenever it asks, and outputs colour/turn pairs.
+    pub fn run(&mut self, brain: &mut Intcode) {
+        let mut colour: Option<Colour> = None;
+        loop {
+            match brain.resume() {
+                Event::NeedInput => brain.input(self.camera().to_word()),
+                Event::Output(word) => match colour.take() {
+                    None => colour = Some(Colour::from_word(word)),
+                    Some(c) => self.paint(c, word),
+                },
+                Event::Halted => break,
+            }
+        }
+    }
+
+    /// How many panels were painted at least once.
+    pub fn painted(&self) -> usize {
+        self.painted.len()
+    }
+
+    /// Draw the white panels.
+    pub fn render(&self) -> String {
+        let white = self.panels
+            .iter()
+            .filter(|(_, c)| **c == Colour::White)
+            .map(|(p, _)| *p)
+            .collect::<HashSet<Coords>>();
+        let x_min = white.iter().map(|p| p.0).min().unwrap_or(0);
+        let x_max = white.iter().map(|p| p.0).max().unwrap_or(-1);
+        let y_min = white.iter().map(|p| p.1).min().unwrap_or(0);
+        let y_max = white.iter().map(|p| p.1).max().unwrap_or(-1);
+        (y_min..=y_max)
+            .map(|y| (x_min..=x_max)
+                        .map(|x| if white.contains(&(x, y)) {'#'} else {' '})
+                        .collect::<String>()
+                        .trim_end()
+                        .to_string()
+            )
+            .collect::<Vec<String>>().join("\n")
+    }
+}
+
+#[test]
+fn test_paint() {
+    // The example from the puzzle
+    let mut robot = Robot::new(Colour::Black);
+    let steps = [(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)];
+    for (colour, turn) in steps.iter() {
+        robot.paint(Colour::from_word(*colour), *turn);
+    }
+    assert_eq!(robot.painted(), 6);
+    assert_eq!(robot.position, (0, -1));
+    assert_eq!(robot.facing, (-1, 0));
+    assert_eq!(robot.render(), "  #\n  #\n##");
+}
+
+#[test]
+fn test_run() {
+    // A brain that echoes the camera back as the colour and always
+    // turns right, so it walks round a square leaving colours be
+    let program = vec![3,100, 4,100, 104,1, 1001,101,1,101, 1008,101,4,102, 1006,102,0, 99];
+    let mut robot = Robot::new(Colour::White);
+    robot.run(&mut Intcode::new(program.clone()));
+    assert_eq!(robot.painted(), 4);
+    assert_eq!(robot.render(), "#");
+
+    let mut robot = Robot::new(Colour::Black);
+    robot.run(&mut Intcode::new(program));
+    assert_eq!(robot.painted(), 4);
+    assert_eq!(robot.render(), "");
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("11", "input").unwrap());
+
+    let mut robot = Robot::new(Colour::Black);
+    robot.run(&mut Intcode::new(program.clone()));
+    println!("{}", robot.painted());
+
+    // The registration identifier shows up when starting on white
+    let mut robot = Robot::new(Colour::White);
+    robot.run(&mut Intcode::new(program));
+    println!("{}", robot.render());
+}
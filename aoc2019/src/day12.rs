@@ -0,0 +1,217 @@
+use std::str::FromStr;
+
+use crate::common::*;
+
+const STEPS: usize = 1000;
+
+/// A moon with a position and velocity in any number of dimensions.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Moon {
+    pub pos: Vec<isize>,
+    pub vel: Vec<isize>,
+}
+
+impl Moon {
+    pub fn new(pos: Vec<isize>) -> Self {
+        let vel = vec![0; pos.len()];
+        Moon { pos, vel }
+    }
+
+    fn potential_energy(&self) -> isize {
+        self.pos.iter().map(|p| p.abs()).sum()
+    }
+
+    fn kinetic_energy(&self) -> isize {
+        self.vel.iter().map(|v| v.abs()).sum()
+    }
+
+    pub fn energy(&self) -> isize {
+        self.potential_energy() * self.kinetic_energy()
+    }
+}
+
+impl FromStr for Moon {
+    type Err = String;
+
+    /// Read a moon like `<x=-1, y=0, z=2>`, with any names for the axes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner = s.trim()
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or(format!("Moon should be in <...>: {:?}", s))?;
+        let pos = inner
+            .split(',')
+            .map(|axis| match axis.split_once('=') {
+                Some((_, value)) => value.trim().parse::<isize>()
+                    .map_err(|e| format!("Bad value in {:?}: {:?}", axis, e)),
+                None => Err(format!("Expected name=value, got {:?}", axis)),
+            })
+            .collect::<Result<Vec<isize>, String>>()?;
+        Ok(Moon::new(pos))
+    }
+}
+
+/// Apply gravity then velocity along one axis, given every moon's
+/// position and velocity along it. Axes don't affect each other.
+fn step_axis(pos: &mut [isize], vel: &mut [isize]) {
+    for i in 0..pos.len() {
+        for j in (i + 1)..pos.len() {
+            let pull = (pos[j] - pos[i]).signum();
+            vel[i] += pull;
+            vel[j] -= pull;
+        }
+    }
+    for (p, v) in pos.iter_mut().zip(vel.iter()) {
+        *p += v;
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct System {
+    pub moons: Vec<Moon>,
+}
+
+impl FromStr for System {
+    type Err = String;
+
+    /// One moon per line; they all need the same number of dimensions.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let moons = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(Moon::from_str)
+            .collect::<Result<Vec<Moon>, String>>()?;
+        if let Some(first) = moons.first() {
+            if moons.iter().any(|m| m.pos.len() != first.pos.len()) {
+                return Err(String::from("Moons have different numbers of dimensions"));
+            }
+        }
+        Ok(System { moons })
+    }
+}
+
+impl System {
+    fn dimensions(&self) -> usize {
+        self.moons.first().map_or(0, |m| m.pos.len())
+    }
+
+    /// Positions and velocities of all the moons along one axis.
+    fn axis(&self, axis: usize) -> (Vec<isize>, Vec<isize>) {
+        self.moons
+            .iter()
+            .map(|m| (m.pos[axis], m.vel[axis]))
+            .unzip()
+    }
+
+    pub fn step(&mut self) {
+        for axis in 0..self.dimensions() {
+            let (mut pos, mut vel) = self.axis(axis);
+            step_axis(&mut pos, &mut vel);
+            for (moon, (p, v)) in self.moons.iter_mut().zip(pos.into_iter().zip(vel)) {
+                moon.pos[axis] = p;
+                moon.vel[axis] = v;
+            }
+        }
+    }
+
+    pub fn energy(&self) -> isize {
+        self.moons.iter().map(|m| m.energy()).sum()
+    }
+
+    /// Steps until one axis gets back to how it started. Every step
+    /// can be undone, so the first repeat is always the start.
+    fn axis_period(&self, axis: usize) -> isize {
+        let start = self.axis(axis);
+        let (mut pos, mut vel) = start.clone();
+        let mut steps = 0;
+        loop {
+            step_axis(&mut pos, &mut vel);
+            steps += 1;
+            if pos == start.0 && vel == start.1 {
+                return steps;
+            }
+        }
+    }
+
+    /// Steps until the whole system gets back to a previous state:
+    /// the axes repeat on their own, so it's the LCM of their periods.
+    /// None if that's too big to count.
+    pub fn period(&self) -> Option<isize> {
+        (0..self.dimensions())
+            .map(|axis| self.axis_period(axis))
+            .try_fold(1, lcm)
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE_1: &str = "<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n<x=4, y=-8, z=8>\n<x=3, y=5, z=-1>";
+#[cfg(test)]
+const EXAMPLE_2: &str = "<x=-8, y=-10, z=0>\n<x=5, y=5, z=10>\n<x=2, y=-7, z=3>\n<x=9, y=-8, z=-3>";
+
+#[test]
+fn test_step() {
+    let mut system = EXAMPLE_1.parse::<System>().unwrap();
+    system.step();
+    assert_eq!(system.moons[0], Moon { pos: vec![2, -1, 1], vel: vec![3, -1, -1] });
+    for _ in 1..10 {
+        system.step();
+    }
+    assert_eq!(system.moons[3], Moon { pos: vec![2, 0, 4], vel: vec![1, -1, -1] });
+    assert_eq!(system.energy(), 179);
+
+    let mut system = EXAMPLE_2.parse::<System>().unwrap();
+    for _ in 0..100 {
+        system.step();
+    }
+    assert_eq!(system.energy(), 1940);
+}
+
+#[test]
+fn test_period() {
+    let system = EXAMPLE_1.parse::<System>().unwrap();
+    assert_eq!(system.period(), Some(2772));
+    let system = EXAMPLE_2.parse::<System>().unwrap();
+    assert_eq!(system.period(), Some(4686774924));
+
+    // Other numbers of bodies and dimensions work too
+    for source in ["<a=1>\n<a=-1>\n<a=4>", "<x=-1, y=0, z=2, w=3>\n<x=2, y=-10, z=-7, w=0>"] {
+        let system = source.parse::<System>().unwrap();
+        let mut stepped = system.clone();
+        stepped.step();
+        let mut steps = 1;
+        while stepped != system {
+            stepped.step();
+            steps += 1;
+        }
+        assert_eq!(system.period(), Some(steps), "{}", source);
+    }
+    assert_eq!(EXAMPLE_1.parse::<System>().unwrap().axis_period(0), 18);
+
+    // Periods too big to multiply out
+    assert_eq!(lcm(isize::MAX, isize::MAX - 1), None);
+    assert_eq!(lcm(4, 6), Some(12));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!("<x=1, y=2>\n<x=1>".parse::<System>().is_err());
+    assert!("<x=1, y=two>".parse::<System>().is_err());
+    assert!("x=1, y=2".parse::<System>().is_err());
+}
+
+pub fn run() {
+    let source = data_file_to_string("12", "input").unwrap();
+    let original = match source.parse::<System>() {
+        Ok(system) => system,
+        Err(e) => panic!("{}", e),
+    };
+    let mut system = original.clone();
+    for _ in 0..STEPS {
+        system.step();
+    }
+    println!("{}", system.energy());
+    match original.period() {
+        Some(period) => println!("{}", period),
+        None => println!("The period's too big to count"),
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use crate::common::*;
+use crate::intcode::{Event,Intcode,Word,parse_program};
+
+type Coords = (Word, Word);
+
+/// Where the score goes instead of a tile.
+const SCORE: Coords = (-1, 0);
+/// Pause between frames when watching.
+const FRAME_DELAY: Duration = Duration::from_millis(20);
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+impl Tile {
+    fn from_word(word: Word) -> Tile {
+        match word {
+            0 => Tile::Empty,
+            1 => Tile::Wall,
+            2 => Tile::Block,
+            3 => Tile::Paddle,
+            4 => Tile::Ball,
+            _ => panic!("Unknown tile {}", word),
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '#',
+            Tile::Block => '=',
+            Tile::Paddle => '_',
+            Tile::Ball => 'o',
+        }
+    }
+}
+
+/// The game ended with blocks still left.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Lost {
+    pub score: Word,
+    pub blocks: usize,
+}
+
+/// The arcade cabinet: an Intcode game that draws `(x, y, tile)`
+/// triples, or `(-1, 0, score)` for the score.
+pub struct Arcade {
+    machine: Intcode,
+    screen: HashMap<Coords, Tile>,
+    score: Word,
+    ball: Option<Coords>,
+    paddle: Option<Coords>,
+}
+
+impl Arcade {
+    pub fn new(program: Vec<Word>) -> Self {
+        Arcade {
+            machine: Intcode::new(program),
+            screen: HashMap::new(),
+            score: 0,
+            ball: None,
+            paddle: None,
+        }
+    }
+
+    /// A cabinet set to play for free.
+    pub fn free_play(mut program: Vec<Word>) -> Self {
+        program[0] = 2;
+        Arcade::new(program)
+    }
+
+    pub fn score(&self) -> Word {
+        self.score
+    }
+
+    /// How many of a tile are on the screen.
+    pub fn count(&self, tile: Tile) -> usize {
+        self.screen.values().filter(|t| **t == tile).count()
+    }
+
+    fn draw(&mut self, x: Word, y: Word, value: Word) {
+        if (x, y) == SCORE {
+            self.score = value;
+            return;
+        }
+        let tile = Tile::from_word(value);
+        match tile {
+            Tile::Ball => self.ball = Some((x, y)),
+            Tile::Paddle => self.paddle = Some((x, y)),
+            _ => {},
+        }
+        self.screen.insert((x, y), tile);
+    }
+
+    /// Tilt the joystick to keep the paddle under the ball.
+    pub fn autopilot(&self) -> Word {
+        match (self.ball, self.paddle) {
+            (Some(ball), Some(paddle)) => (ball.0 - paddle.0).signum(),
+            _ => 0,
+        }
+    }
+
+    /// Run the game with the autopilot on the joystick until it
+    /// halts, giving back the score if every block got broken.
+    /// `on_frame` sees the screen each time the game waits for the
+    /// joystick, and at the end.
+    pub fn play(&mut self, mut on_frame: impl FnMut(&Arcade)) -> Result<Word, Lost> {
+        let mut triple: Vec<Word> = Vec::with_capacity(3);
+        loop {
+            match self.machine.resume() {
+                Event::Output(value) => {
+                    triple.push(value);
+                    if triple.len() == 3 {
+                        self.draw(triple[0], triple[1], triple[2]);
+                        triple.clear();
+                    }
+                },
+                Event::NeedInput => {
+                    on_frame(self);
+                    let joystick = self.autopilot();
+                    self.machine.input(joystick);
+                },
+                Event::Halted => {
+                    on_frame(self);
+                    break;
+                },
+            }
+        }
+        match self.count(Tile::Block) {
+            0 => Ok(self.score),
+            blocks => Err(Lost { score: self.score, blocks }),
+        }
+    }
+
+    /// Draw the screen, with the score above it.
+    pub fn render(&self) -> String {
+        let width = self.screen.keys().map(|p| p.0 + 1).max().unwrap_or(0);
+        let height = self.screen.keys().map(|p| p.1 + 1).max().unwrap_or(0);
+        let mut rows = vec![format!("Score: {}", self.score)];
+        for y in 0..height {
+            let row = (0..width)
+                .map(|x| self.screen.get(&(x, y)).map_or(' ', |t| t.to_char()))
+                .collect::<String>();
+            rows.push(String::from(row.trim_end()));
+        }
+        rows.join("\n")
+    }
+}
+
+/// Show a frame in the terminal, clearing what was there.
+pub fn watch(arcade: &Arcade) {
+    println!("\x1b[2J\x1b[H{}", arcade.render());
+    thread::sleep(FRAME_DELAY);
+}
+
+#[test]
+fn test_play() {
+    // Draws a wall, two blocks, the paddle and the ball, then scores
+    // the joystick position plus 10
+    let program = vec![
+        104,0, 104,0, 104,1,
+        104,1, 104,0, 104,2,
+        104,2, 104,0, 104,2,
+        104,3, 104,1, 104,3,
+        104,5, 104,1, 104,4,
+        3,100, 1001,100,10,101,
+        104,-1, 104,0, 4,101,
+        99,
+    ];
+    let mut arcade = Arcade::new(program.clone());
+    let mut frames = Vec::new();
+    let result = arcade.play(|a| frames.push(a.render()));
+    // Ball's right of the paddle, so the joystick goes right (1), but
+    // the blocks are still there at the end
+    assert_eq!(result, Err(Lost { score: 11, blocks: 2 }));
+    assert_eq!(arcade.count(Tile::Block), 2);
+    assert_eq!(frames, vec![
+        String::from("Score: 0\n#==\n   _ o"),
+        String::from("Score: 11\n#==\n   _ o"),
+    ]);
+
+    // The same, but breaking both blocks before the end
+    let mut program = program;
+    program.pop();
+    program.extend(vec![104,1, 104,0, 104,0, 104,2, 104,0, 104,0, 99]);
+    let mut arcade = Arcade::new(program);
+    assert_eq!(arcade.play(|_| {}), Ok(11));
+    assert_eq!(arcade.count(Tile::Block), 0);
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("13", "input").unwrap());
+
+    // Without coins the game just draws the screen, so it's never won
+    let mut arcade = Arcade::new(program.clone());
+    let _ = arcade.play(|_| {});
+    println!("{}", arcade.count(Tile::Block));
+
+    let mut arcade = Arcade::free_play(program);
+    report(arcade.play(|_| {}));
+}
+
+/// Watch the autopilot play through in the terminal.
+pub fn watch_autopilot() {
+    let program = parse_program(&data_file_to_string("13", "input").unwrap());
+    let mut arcade = Arcade::free_play(program);
+    report(arcade.play(watch));
+}
+
+fn report(result: Result<Word, Lost>) {
+    match result {
+        Ok(score) => println!("{}", score),
+        Err(lost) => println!("Lost with {} blocks left, scoring {}", lost.blocks, lost.score),
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{HashMap,HashSet};
+use std::str::FromStr;
+
+use crate::common::*;
+
+const ORE: &str = "ORE";
+const FUEL: &str = "FUEL";
+const CARGO_ORE: u64 = 1_000_000_000_000;
+
+/// Problems with a list of reactions.
+#[derive(Debug,PartialEq)]
+pub enum ParseError {
+    /// A line (numbered from 1) that isn't a reaction
+    BadLine(usize, String),
+    /// More than one reaction makes this
+    Duplicate(String),
+    /// Nothing makes this, and it isn't ORE (or nothing makes FUEL)
+    Undefined(String),
+    /// This ends up needing itself
+    Cycle(String),
+}
+
+/// Makes `output` of a chemical from some amounts of others.
+#[derive(Debug,Clone,PartialEq)]
+struct Reaction {
+    output: u64,
+    inputs: Vec<(String, u64)>,
+}
+
+/// The recipe graph, with chemicals in an order where everything comes
+/// before what it's made from.
+#[derive(Debug)]
+pub struct Nanofactory {
+    reactions: HashMap<String, Reaction>,
+    order: Vec<String>,
+}
+
+/// Read something like `7 A`.
+fn parse_amount(s: &str) -> Option<(String, u64)> {
+    let (amount, chemical) = s.trim().split_once(' ')?;
+    let amount = amount.parse::<u64>().ok()?;
+    let chemical = chemical.trim();
+    if chemical.is_empty() { None } else { Some((String::from(chemical), amount)) }
+}
+
+impl FromStr for Nanofactory {
+    type Err = ParseError;
+
+    /// One reaction per line, like `7 A, 1 B => 1 C`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reactions: HashMap<String, Reaction> = HashMap::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = || ParseError::BadLine(i + 1, String::from(line));
+            let (inputs, output) = line.split_once("=>").ok_or_else(bad_line)?;
+            let (chemical, output) = parse_amount(output).ok_or_else(bad_line)?;
+            let inputs = inputs
+                .split(',')
+                .map(parse_amount)
+                .collect::<Option<Vec<(String, u64)>>>()
+                .ok_or_else(bad_line)?;
+            if output == 0 || chemical == ORE {
+                return Err(bad_line());
+            }
+            if reactions.contains_key(&chemical) {
+                return Err(ParseError::Duplicate(chemical));
+            }
+            reactions.insert(chemical, Reaction { output, inputs });
+        }
+
+        if !reactions.contains_key(FUEL) {
+            return Err(ParseError::Undefined(String::from(FUEL)));
+        }
+        for reaction in reactions.values() {
+            for (input, _) in reaction.inputs.iter() {
+                if input != ORE && !reactions.contains_key(input) {
+                    return Err(ParseError::Undefined(input.clone()));
+                }
+            }
+        }
+        let order = Nanofactory::order(&reactions)?;
+        Ok(Nanofactory { reactions, order })
+    }
+}
+
+impl Nanofactory {
+    /// Sort the chemicals so that each comes before everything it's
+    /// made from (depth-first, spotting cycles on the way).
+    fn order(reactions: &HashMap<String, Reaction>) -> Result<Vec<String>, ParseError> {
+        fn visit(chemical: &str, reactions: &HashMap<String, Reaction>,
+                 visiting: &mut HashSet<String>, done: &mut HashSet<String>,
+                 order: &mut Vec<String>) -> Result<(), ParseError> {
+            // ORE isn't made from anything
+            if done.contains(chemical) || !reactions.contains_key(chemical) {
+                return Ok(());
+            }
+            if !visiting.insert(String::from(chemical)) {
+                return Err(ParseError::Cycle(String::from(chemical)));
+            }
+            for (input, _) in reactions[chemical].inputs.iter() {
+                visit(input, reactions, visiting, done, order)?;
+            }
+            visiting.remove(chemical);
+            done.insert(String::from(chemical));
+            order.push(String::from(chemical));
+            Ok(())
+        }
+
+        let mut chemicals = reactions.keys().collect::<Vec<&String>>();
+        chemicals.sort();
+        let mut order = Vec::new();
+        let (mut visiting, mut done) = (HashSet::new(), HashSet::new());
+        for chemical in chemicals {
+            visit(chemical, reactions, &mut visiting, &mut done, &mut order)?;
+        }
+        // Inputs were pushed before what they make, so flip it round
+        order.reverse();
+        Ok(order)
+    }
+
+    /// ORE needed to make some FUEL, and the chemicals left over.
+    /// None if the amounts get too big for a u64.
+    pub fn ore_for(&self, fuel: u64) -> Option<(u64, HashMap<String, u64>)> {
+        let mut needs: HashMap<&str, u64> = HashMap::new();
+        needs.insert(FUEL, fuel);
+        let mut leftovers = HashMap::new();
+        for chemical in self.order.iter() {
+            let need = needs.remove(chemical.as_str()).unwrap_or(0);
+            if need == 0 {
+                continue;
+            }
+            let reaction = &self.reactions[chemical];
+            let batches = need.div_ceil(reaction.output);
+            let spare = batches.checked_mul(reaction.output)? - need;
+            if spare > 0 {
+                leftovers.insert(chemical.clone(), spare);
+            }
+            for (input, amount) in reaction.inputs.iter() {
+                let total = needs.entry(input.as_str()).or_insert(0);
+                *total = total.checked_add(batches.checked_mul(*amount)?)?;
+            }
+        }
+        Some((needs.get(ORE).copied().unwrap_or(0), leftovers))
+    }
+
+    /// Most FUEL that can be made from some ORE, by binary search.
+    pub fn max_fuel(&self, ore: u64) -> u64 {
+        // Amounts too big to work out are certainly too much
+        let enough = |fuel| self.ore_for(fuel).is_some_and(|(needed, _)| needed <= ore);
+        // Find a top end that needs too much ore, then close in
+        let mut lo = 0;
+        let mut hi = 1;
+        while enough(hi) {
+            lo = hi;
+            hi = match hi.checked_mul(2) {
+                Some(hi) => hi,
+                None if enough(u64::MAX) => return u64::MAX,
+                None => u64::MAX,
+            };
+        }
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if enough(mid) {
+                lo = mid;
+            }
+            else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE_1: &str = "\
+10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL";
+
+#[cfg(test)]
+const EXAMPLE_3: &str = "\
+157 ORE => 5 NZVS
+165 ORE => 6 DCFZ
+44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
+12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
+179 ORE => 7 PSHF
+177 ORE => 5 HKGWZ
+7 DCFZ, 7 PSHF => 2 XJWVT
+165 ORE => 2 GPVTF
+3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT";
+
+#[cfg(test)]
+const EXAMPLE_4: &str = "\
+2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG
+17 NVRVD, 3 JNWZP => 8 VPVL
+53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL
+22 VJHF, 37 MNCFX => 5 FWMGM
+139 ORE => 4 NVRVD
+144 ORE => 7 JNWZP
+5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC
+5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV
+145 ORE => 6 MNCFX
+1 NVRVD => 8 CXFTF
+1 VJHF, 6 MNCFX => 4 RFSQX
+176 ORE => 6 VJHF";
+
+#[test]
+fn test_ore_for() {
+    let factory = EXAMPLE_1.parse::<Nanofactory>().unwrap();
+    let (ore, leftovers) = factory.ore_for(1).unwrap();
+    assert_eq!(ore, 31);
+    assert_eq!(leftovers, [(String::from("A"), 2)].iter().cloned().collect());
+
+    let factory = EXAMPLE_3.parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.ore_for(1).unwrap().0, 13312);
+    let factory = EXAMPLE_4.parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.ore_for(1).unwrap().0, 180697);
+    assert_eq!(factory.ore_for(u64::MAX), None);
+}
+
+#[test]
+fn test_max_fuel() {
+    let factory = EXAMPLE_3.parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.max_fuel(CARGO_ORE), 82892753);
+    let factory = EXAMPLE_4.parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.max_fuel(CARGO_ORE), 5586022);
+    let factory = EXAMPLE_1.parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.max_fuel(30), 0);
+    // Running out of numbers before running out of ore
+    let factory = "1 ORE => 1 FUEL".parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.max_fuel(u64::MAX), u64::MAX);
+    assert_eq!(factory.max_fuel(u64::MAX - 1), u64::MAX - 1);
+    let factory = "2 ORE => 1 FUEL".parse::<Nanofactory>().unwrap();
+    assert_eq!(factory.max_fuel(u64::MAX), u64::MAX / 2);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!("1 ORE => 1 A\n1 A, 1 B => 1 FUEL".parse::<Nanofactory>().err(),
+               Some(ParseError::Undefined(String::from("B"))));
+    assert_eq!("1 ORE, 1 B => 1 A\n1 A => 1 B\n1 A => 1 FUEL".parse::<Nanofactory>().err(),
+               Some(ParseError::Cycle(String::from("A"))));
+    assert_eq!("1 ORE => 1 A\n2 ORE => 1 A".parse::<Nanofactory>().err(),
+               Some(ParseError::Duplicate(String::from("A"))));
+    assert_eq!("1 ORE => 1 A\n1 A -> 1 FUEL".parse::<Nanofactory>().err(),
+               Some(ParseError::BadLine(2, String::from("1 A -> 1 FUEL"))));
+    assert_eq!("1 ORE => 1 A".parse::<Nanofactory>().err(),
+               Some(ParseError::Undefined(String::from(FUEL))));
+    assert_eq!("1 ORE => A".parse::<Nanofactory>().err(),
+               Some(ParseError::BadLine(1, String::from("1 ORE => A"))));
+}
+
+pub fn run() {
+    let source = data_file_to_string("14", "input").unwrap();
+    let factory = match source.parse::<Nanofactory>() {
+        Ok(factory) => factory,
+        Err(e) => panic!("{:?}", e),
+    };
+    match factory.ore_for(1) {
+        Some((ore, _)) => println!("{}", ore),
+        None => println!("Too much ORE to count"),
+    }
+    println!("{}", factory.max_fuel(CARGO_ORE));
+}
@@ -0,0 +1,255 @@
+use std::collections::{HashMap,VecDeque};
+
+use crate::common::*;
+use crate::intcode::{Event,Intcode,Word,parse_program};
+
+type Coords = (isize, isize);
+
+/// Where the droid starts.
+const START: Coords = (0, 0);
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Direction {
+    North,
+    South,
+    West,
+    East,
+}
+
+const DIRECTIONS: [Direction; 4] =
+    [Direction::North, Direction::South, Direction::West, Direction::East];
+
+impl Direction {
+    /// Movement command for the droid.
+    fn command(self) -> Word {
+        match self {
+            Direction::North => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+            Direction::East => 4,
+        }
+    }
+
+    fn reverse(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+        }
+    }
+
+    /// Step from a position (y goes down, so north is negative y).
+    fn step(self, (x, y): Coords) -> Coords {
+        match self {
+            Direction::North => (x, y - 1),
+            Direction::South => (x, y + 1),
+            Direction::West => (x - 1, y),
+            Direction::East => (x + 1, y),
+        }
+    }
+}
+
+/// What the droid found when it tried to move.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Status {
+    HitWall,
+    Moved,
+    FoundOxygen,
+}
+
+impl Status {
+    fn from_word(word: Word) -> Status {
+        match word {
+            0 => Status::HitWall,
+            1 => Status::Moved,
+            2 => Status::FoundOxygen,
+            _ => panic!("Unknown status {}", word),
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Cell {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+/// The map the droid has discovered.
+#[derive(Debug,Default)]
+pub struct Maze {
+    cells: HashMap<Coords, Cell>,
+    oxygen: Option<Coords>,
+}
+
+impl Maze {
+    /// Map the whole maze depth-first, backtracking out of dead ends.
+    /// `try_move` sends the droid a step and says what happened.
+    pub fn explore(mut try_move: impl FnMut(Direction) -> Status) -> Maze {
+        let mut maze = Maze::default();
+        maze.cells.insert(START, Cell::Open);
+        let mut position = START;
+        // Moves taken from the start to get here
+        let mut path: Vec<Direction> = Vec::new();
+        loop {
+            let unknown = DIRECTIONS
+                .iter()
+                .find(|d| !maze.cells.contains_key(&d.step(position)));
+            match unknown {
+                Some(direction) => {
+                    let next = direction.step(position);
+                    let cell = match try_move(*direction) {
+                        Status::HitWall => Cell::Wall,
+                        Status::Moved => Cell::Open,
+                        Status::FoundOxygen => Cell::Oxygen,
+                    };
+                    maze.cells.insert(next, cell);
+                    if cell == Cell::Oxygen {
+                        maze.oxygen = Some(next);
+                    }
+                    if cell != Cell::Wall {
+                        position = next;
+                        path.push(*direction);
+                    }
+                },
+                None => {
+                    // Everything round here is known, so go back a step
+                    let back = match path.pop() {
+                        Some(direction) => direction.reverse(),
+                        None => break,
+                    };
+                    if try_move(back) == Status::HitWall {
+                        panic!("Droid couldn't go back the way it came");
+                    }
+                    position = back.step(position);
+                },
+            }
+        }
+        maze
+    }
+
+    /// Explore with the repair droid's Intcode program.
+    pub fn explore_with(droid: &mut Intcode) -> Maze {
+        Maze::explore(|direction| {
+            droid.input(direction.command());
+            match droid.resume() {
+                Event::Output(status) => Status::from_word(status),
+                e => panic!("Droid didn't report back: {:?}", e),
+            }
+        })
+    }
+
+    /// Steps from a position to everywhere reachable, breadth-first.
+    fn distances(&self, from: Coords) -> HashMap<Coords, usize> {
+        let mut distances = HashMap::new();
+        distances.insert(from, 0);
+        let mut queue = VecDeque::from(vec![from]);
+        while let Some(position) = queue.pop_front() {
+            let steps = distances[&position] + 1;
+            for direction in DIRECTIONS.iter() {
+                let next = direction.step(position);
+                let open = matches!(self.cells.get(&next), Some(Cell::Open) | Some(Cell::Oxygen));
+                if open && !distances.contains_key(&next) {
+                    distances.insert(next, steps);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Fewest moves from the start to the oxygen system.
+    pub fn shortest_path(&self) -> Option<usize> {
+        self.distances(START).get(&self.oxygen?).copied()
+    }
+
+    /// Minutes for oxygen to spread everywhere from the oxygen system.
+    pub fn fill_time(&self) -> Option<usize> {
+        self.distances(self.oxygen?).values().max().copied()
+    }
+
+    /// Draw the map, with `D` where the droid started and blanks where
+    /// nobody knows.
+    pub fn render(&self) -> String {
+        let x_min = self.cells.keys().map(|p| p.0).min().unwrap_or(0);
+        let x_max = self.cells.keys().map(|p| p.0).max().unwrap_or(0);
+        let y_min = self.cells.keys().map(|p| p.1).min().unwrap_or(0);
+        let y_max = self.cells.keys().map(|p| p.1).max().unwrap_or(0);
+        (y_min..=y_max)
+            .map(|y| {
+                let row = (x_min..=x_max)
+                    .map(|x| match self.cells.get(&(x, y)) {
+                        _ if (x, y) == START => 'D',
+                        Some(Cell::Wall) => '#',
+                        Some(Cell::Open) => '.',
+                        Some(Cell::Oxygen) => 'O',
+                        None => ' ',
+                    })
+                    .collect::<String>();
+                String::from(row.trim_end())
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = " ##\n#D.##\n#.#..#\n#.O.#\n ###";
+
+/// Pretend to be the droid in a maze drawn like `render` does.
+#[cfg(test)]
+fn fake_droid(map: &str) -> impl FnMut(Direction) -> Status {
+    let mut cells = HashMap::new();
+    let mut position = START;
+    for (y, row) in map.lines().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let at = (x as isize, y as isize);
+            if c == 'D' {
+                position = at;
+            }
+            cells.insert(at, c);
+        }
+    }
+    // Shift so the droid starts at the origin
+    let cells = cells
+        .into_iter()
+        .map(|((x, y), c)| ((x - position.0, y - position.1), c))
+        .collect::<HashMap<Coords, char>>();
+    let mut position = START;
+    move |direction| {
+        let next = direction.step(position);
+        match cells.get(&next).copied().unwrap_or('#') {
+            '#' | ' ' => Status::HitWall,
+            c => {
+                position = next;
+                if c == 'O' { Status::FoundOxygen } else { Status::Moved }
+            },
+        }
+    }
+}
+
+#[test]
+fn test_explore() {
+    let maze = Maze::explore(fake_droid(EXAMPLE));
+    assert_eq!(maze.render(), EXAMPLE);
+    assert_eq!(maze.shortest_path(), Some(3));
+    assert_eq!(maze.fill_time(), Some(4));
+
+    // No oxygen system to be found
+    let maze = Maze::explore(fake_droid("###\n#D#\n###"));
+    // Corners never get looked at
+    assert_eq!(maze.render(), " #\n#D#\n #");
+    assert_eq!(maze.shortest_path(), None);
+    assert_eq!(maze.fill_time(), None);
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("15", "input").unwrap());
+    let maze = Maze::explore_with(&mut Intcode::new(program));
+    println!("{}", maze.render());
+    match (maze.shortest_path(), maze.fill_time()) {
+        (Some(path), Some(fill)) => println!("{}\n{}", path, fill),
+        _ => println!("No oxygen system found"),
+    }
+}
@@ -0,0 +1,157 @@
+use crate::common::*;
+
+const BASE_PATTERN: [i64; 4] = [0, 1, 0, -1];
+const PHASES: usize = 100;
+const REPEATS: usize = 10_000;
+/// The message offset is this many digits at the start of the signal
+const OFFSET_DIGITS: usize = 7;
+const MESSAGE_DIGITS: usize = 8;
+
+pub fn parse_signal(source: &str) -> Vec<u8> {
+    source
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .map(|c| c.to_digit(10).unwrap() as u8)
+        .collect()
+}
+
+fn digits_to_string(digits: &[u8]) -> String {
+    digits.iter().map(|d| char::from(b'0' + d)).collect()
+}
+
+/// One phase of FFT with any base pattern.
+/// Output digit i repeats each pattern value i + 1 times (skipping the
+/// very first one), so the input splits into runs that all get the
+/// same multiplier. With prefix sums each run costs O(1), making the
+/// whole phase O(n log n) instead of O(n²).
+/// None for an empty pattern.
+pub fn phase(signal: &[u8], pattern: &[i64]) -> Option<Vec<u8>> {
+    if pattern.is_empty() {
+        return None;
+    }
+    let mut prefix = vec![0i64; signal.len() + 1];
+    for (i, d) in signal.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + *d as i64;
+    }
+    Some((0..signal.len())
+        .map(|i| {
+            let run = i + 1;
+            // The first run is one short, because of the skip
+            let mut total = pattern[0] * prefix[run - 1];
+            let mut start = run - 1;
+            let mut k = 1;
+            while start < signal.len() {
+                let end = (start + run).min(signal.len());
+                let multiplier = pattern[k % pattern.len()];
+                if multiplier != 0 {
+                    total += multiplier * (prefix[end] - prefix[start]);
+                }
+                start = end;
+                k += 1;
+            }
+            (total.abs() % 10) as u8
+        })
+        .collect())
+}
+
+/// Run a number of phases with a base pattern (None if it's empty).
+pub fn fft(signal: &[u8], phases: usize, pattern: &[i64]) -> Option<Vec<u8>> {
+    if pattern.is_empty() {
+        return None;
+    }
+    (0..phases).try_fold(signal.to_vec(), |s, _| phase(&s, pattern))
+}
+
+#[test]
+fn test_fft() {
+    let mut signal = parse_signal("12345678");
+    for expected in ["48226158", "34040438", "03415518", "01029498"].iter() {
+        signal = phase(&signal, &BASE_PATTERN).unwrap();
+        assert_eq!(digits_to_string(&signal), *expected);
+    }
+    let tests = [
+        ("80871224585914546619083218645595", "24176176"),
+        ("19617804207202209144916044189917", "73745418"),
+        ("69317163492948606335995924319873", "52432133"),
+    ];
+    for (input, expected) in tests.iter() {
+        let output = fft(&parse_signal(input), PHASES, &BASE_PATTERN).unwrap();
+        assert_eq!(digits_to_string(&output[..MESSAGE_DIGITS]), *expected);
+    }
+
+    // Any pattern works
+    let output = phase(&parse_signal("12345678"), &[1]).unwrap();
+    assert_eq!(digits_to_string(&output), "66666666");
+    let signal = parse_signal("80871224585914546619083218645595");
+    let pattern = [2, 1, -1];
+    let naive = (0..signal.len())
+        .map(|i| {
+            let total = signal
+                .iter()
+                .enumerate()
+                .map(|(j, d)| pattern[((j + 1) / (i + 1)) % pattern.len()] * *d as i64)
+                .sum::<i64>();
+            (total.abs() % 10) as u8
+        })
+        .collect::<Vec<u8>>();
+    assert_eq!(phase(&signal, &pattern), Some(naive));
+
+    // Except no pattern at all
+    assert_eq!(phase(&signal, &[]), None);
+    assert_eq!(fft(&signal, PHASES, &[]), None);
+    assert_eq!(fft(&signal, 0, &[]), None);
+}
+
+/// Find the message in the real signal (the input repeated many times)
+/// after all the phases, at the offset given by its first digits.
+///
+/// In the back half of the signal the pattern is all zeros then all
+/// ones, so each digit is just the sum of those after it: one pass
+/// from the end per phase, and only from the offset on. That's no use
+/// if the offset's in the front half, so that gives None.
+pub fn decode_message(signal: &[u8], repeats: usize, phases: usize) -> Option<String> {
+    let offset = signal
+        .iter()
+        .take(OFFSET_DIGITS)
+        .fold(0usize, |acc, d| acc * 10 + *d as usize);
+    let total = signal.len() * repeats;
+    if offset < total / 2 || offset + MESSAGE_DIGITS > total {
+        return None;
+    }
+    let mut tail = (offset..total)
+        .map(|i| signal[i % signal.len()])
+        .collect::<Vec<u8>>();
+    for _ in 0..phases {
+        let mut sum = 0u8;
+        for d in tail.iter_mut().rev() {
+            sum = (sum + *d) % 10;
+            *d = sum;
+        }
+    }
+    Some(digits_to_string(&tail[..MESSAGE_DIGITS]))
+}
+
+#[test]
+fn test_decode_message() {
+    let tests = [
+        ("03036732577212944063491565474664", "84462026"),
+        ("02935109699940807407585447034323", "78725270"),
+        ("03081770884921959731165446850517", "53553731"),
+    ];
+    for (input, expected) in tests.iter() {
+        let message = decode_message(&parse_signal(input), REPEATS, PHASES);
+        assert_eq!(message.as_deref(), Some(*expected));
+    }
+    // Offset in the front half
+    assert_eq!(decode_message(&parse_signal("00000010"), 10, PHASES), None);
+}
+
+pub fn run() {
+    let signal = parse_signal(&data_file_to_string("16", "input").unwrap());
+    let output = fft(&signal, PHASES, &BASE_PATTERN).unwrap();
+    println!("{}", digits_to_string(&output[..MESSAGE_DIGITS]));
+    match decode_message(&signal, REPEATS, PHASES) {
+        Some(message) => println!("{}", message),
+        None => println!("Message offset isn't in the back half of the signal"),
+    }
+}
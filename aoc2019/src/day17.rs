@@ -0,0 +1,303 @@
+use crate::common::*;
+use crate::intcode::{Intcode,Word,parse_program};
+
+type Coords = (isize, isize);
+
+/// Longest a movement routine or function can be, not counting the newline
+const MAX_ROUTINE: usize = 20;
+const FUNCTION_NAMES: [&str; 3] = ["A", "B", "C"];
+
+/// What the cameras can see: scaffold, open space and the robot.
+pub struct Scaffold {
+    rows: Vec<Vec<char>>,
+}
+
+impl From<&str> for Scaffold {
+    fn from(view: &str) -> Self {
+        let rows = view
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect())
+            .collect();
+        Scaffold { rows }
+    }
+}
+
+/// Step in a direction (y goes down).
+fn step((x, y): Coords, (dx, dy): Coords) -> Coords {
+    (x + dx, y + dy)
+}
+
+impl Scaffold {
+    fn get(&self, (x, y): Coords) -> char {
+        if x < 0 || y < 0 {
+            return '.';
+        }
+        self.rows
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or('.')
+    }
+
+    /// Scaffold, including under the robot (unless it's fallen off).
+    fn is_scaffold(&self, at: Coords) -> bool {
+        matches!(self.get(at), '#' | '^' | 'v' | '<' | '>')
+    }
+
+    /// Places where scaffold carries on in all four directions.
+    pub fn intersections(&self) -> Vec<Coords> {
+        let mut found = Vec::new();
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in 0..row.len() {
+                let at = (x as isize, y as isize);
+                let all = [at, (at.0 - 1, at.1), (at.0 + 1, at.1), (at.0, at.1 - 1), (at.0, at.1 + 1)]
+                    .iter()
+                    .all(|p| self.is_scaffold(*p));
+                if all {
+                    found.push(at);
+                }
+            }
+        }
+        found
+    }
+
+    pub fn alignment_sum(&self) -> isize {
+        self.intersections().iter().map(|(x, y)| x * y).sum()
+    }
+
+    /// Where the robot is and which way it's facing.
+    fn robot(&self) -> Option<(Coords, Coords)> {
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                let facing = match c {
+                    '^' => (0, -1),
+                    'v' => (0, 1),
+                    '<' => (-1, 0),
+                    '>' => (1, 0),
+                    _ => continue,
+                };
+                return Some(((x as isize, y as isize), facing));
+            }
+        }
+        None
+    }
+
+    /// The walk over the whole scaffold: turn towards wherever the
+    /// scaffold goes, go as far as possible, and repeat until it ends.
+    /// Gives moves like `R`, `8`.
+    pub fn path(&self) -> Vec<String> {
+        let mut moves = Vec::new();
+        let (mut at, mut facing) = match self.robot() {
+            Some(robot) => robot,
+            None => return moves,
+        };
+        loop {
+            let (dx, dy) = facing;
+            let left = (dy, -dx);
+            let right = (-dy, dx);
+            if self.is_scaffold(step(at, left)) {
+                moves.push(String::from("L"));
+                facing = left;
+            }
+            else if self.is_scaffold(step(at, right)) {
+                moves.push(String::from("R"));
+                facing = right;
+            }
+            else {
+                break;
+            }
+            let mut distance = 0;
+            while self.is_scaffold(step(at, facing)) {
+                at = step(at, facing);
+                distance += 1;
+            }
+            moves.push(distance.to_string());
+        }
+        moves
+    }
+}
+
+/// Movement routines for the vacuum robot.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Routines {
+    pub main: String,
+    pub functions: Vec<String>,
+}
+
+/// Join moves into a routine if it's short enough.
+fn routine(moves: &[String]) -> Option<String> {
+    let routine = moves.join(",");
+    if routine.len() <= MAX_ROUTINE { Some(routine) } else { None }
+}
+
+/// Split a path into a main routine calling up to three functions,
+/// with everything short enough for the robot's memory.
+/// Each function is a turn followed by a distance, so functions are
+/// built from whole pairs of moves.
+pub fn compress(path: &[String]) -> Option<Routines> {
+    fn search(path: &[String], functions: &mut Vec<Vec<String>>,
+              calls: &mut Vec<usize>) -> bool {
+        if path.is_empty() {
+            return true;
+        }
+        let calls_len = calls.len() * 2 + 1;
+        if calls_len > MAX_ROUTINE {
+            return false;
+        }
+        // Carry on with a function we've already got
+        for i in 0..functions.len() {
+            if path.starts_with(&functions[i]) {
+                calls.push(i);
+                let rest = &path[functions[i].len()..];
+                if search(rest, functions, calls) {
+                    return true;
+                }
+                calls.pop();
+            }
+        }
+        // Or make a new one out of the start of what's left
+        if functions.len() < FUNCTION_NAMES.len() {
+            let mut len = 2;
+            while len <= path.len() && routine(&path[..len]).is_some() {
+                functions.push(path[..len].to_vec());
+                calls.push(functions.len() - 1);
+                if search(&path[len..], functions, calls) {
+                    return true;
+                }
+                calls.pop();
+                functions.pop();
+                len += 2;
+            }
+        }
+        false
+    }
+
+    let mut functions = Vec::new();
+    let mut calls = Vec::new();
+    if !search(path, &mut functions, &mut calls) {
+        return None;
+    }
+    let main = calls
+        .iter()
+        .map(|i| FUNCTION_NAMES[*i])
+        .collect::<Vec<&str>>()
+        .join(",");
+    let functions = functions
+        .iter()
+        .map(|f| routine(f).unwrap())
+        .collect();
+    Some(Routines { main, functions })
+}
+
+/// Look through the cameras.
+pub fn camera(program: &[Word]) -> Scaffold {
+    let mut robot = Intcode::new(program.to_vec());
+    let (view, _) = robot.read_ascii();
+    Scaffold::from(view.as_str())
+}
+
+/// Wake the vacuum robot up, give it the routines and get the amount of
+/// dust it collected.
+pub fn collect_dust(program: &[Word], routines: &Routines) -> Option<Word> {
+    let mut program = program.to_vec();
+    program[0] = 2;
+    let mut robot = Intcode::new(program);
+    robot.input_line(&routines.main);
+    for i in 0..FUNCTION_NAMES.len() {
+        // Unused functions still need a line
+        robot.input_line(routines.functions.get(i).map_or("", |f| f.as_str()));
+    }
+    // No continuous video feed
+    robot.input_line("n");
+    let (_, dust) = robot.read_ascii();
+    dust.last().copied()
+}
+
+#[cfg(test)]
+const EXAMPLE_1: &str = "\
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..";
+
+#[cfg(test)]
+const EXAMPLE_2: &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......";
+
+#[test]
+fn test_alignment() {
+    let scaffold = Scaffold::from(EXAMPLE_1);
+    assert_eq!(scaffold.intersections(), vec![(2, 2), (2, 4), (6, 4), (10, 4)]);
+    assert_eq!(scaffold.alignment_sum(), 76);
+}
+
+#[test]
+fn test_path_and_compress() {
+    let path = Scaffold::from(EXAMPLE_2).path();
+    assert_eq!(path.join(","), "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2");
+
+    let routines = compress(&path).unwrap();
+    assert!(routines.main.len() <= MAX_ROUTINE);
+    assert!(routines.functions.iter().all(|f| f.len() <= MAX_ROUTINE));
+    let expanded = routines.main
+        .split(',')
+        .map(|name| {
+            let i = FUNCTION_NAMES.iter().position(|n| *n == name).unwrap();
+            routines.functions[i].as_str()
+        })
+        .collect::<Vec<&str>>()
+        .join(",");
+    assert_eq!(expanded, path.join(","));
+
+    // Too many different moves to fit in three functions
+    let path = (10..23)
+        .flat_map(|d| vec![String::from(if d % 2 == 0 {"L"} else {"R"}), d.to_string()])
+        .collect::<Vec<String>>();
+    assert_eq!(compress(&path), None);
+}
+
+#[test]
+fn test_camera() {
+    // A program that just prints the first example
+    let program = EXAMPLE_1
+        .bytes()
+        .flat_map(|b| vec![104, Word::from(b)])
+        .chain(std::iter::once(99))
+        .collect::<Vec<Word>>();
+    assert_eq!(camera(&program).alignment_sum(), 76);
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("17", "input").unwrap());
+    let scaffold = camera(&program);
+    println!("{}", scaffold.alignment_sum());
+
+    let path = scaffold.path();
+    let routines = match compress(&path) {
+        Some(routines) => routines,
+        None => panic!("Couldn't fit the path {} into routines", path.join(",")),
+    };
+    println!("{:?}", routines);
+    match collect_dust(&program, &routines) {
+        Some(dust) => println!("{}", dust),
+        None => println!("The robot didn't say how much dust it got"),
+    }
+}
@@ -0,0 +1,257 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap,HashMap,HashSet,VecDeque};
+
+use crate::common::*;
+
+type Coords = (usize, usize);
+/// One bit per key (or door) letter
+type KeySet = u32;
+
+/// Getting from one place to a key.
+#[derive(Debug,Clone)]
+struct Route {
+    /// Which key (0 for `a`)
+    key: usize,
+    distance: usize,
+    /// Doors in the way
+    doors: KeySet,
+    /// Other keys passed over on the way
+    keys: KeySet,
+}
+
+/// The vault's map.
+pub struct Vault {
+    grid: Vec<Vec<u8>>,
+}
+
+impl From<&str> for Vault {
+    fn from(map: &str) -> Self {
+        let grid = map
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.bytes().collect())
+            .collect();
+        Vault { grid }
+    }
+}
+
+impl Vault {
+    fn get(&self, (x, y): Coords) -> u8 {
+        self.grid.get(y).and_then(|row| row.get(x)).copied().unwrap_or(b'#')
+    }
+
+    fn find(&self, wanted: impl Fn(u8) -> bool) -> Vec<(Coords, u8)> {
+        let mut found = Vec::new();
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                if wanted(*c) {
+                    found.push(((x, y), *c));
+                }
+            }
+        }
+        found
+    }
+
+    /// Swap the single entrance for a wall cross with four entrances
+    /// round it, one for each robot. None unless there's exactly one
+    /// entrance with open space all round it.
+    pub fn split(&self) -> Option<Vault> {
+        let entrances = self.find(|c| c == b'@');
+        let (x, y) = match entrances.as_slice() {
+            [(at, _)] => *at,
+            _ => return None,
+        };
+        if x == 0 || y == 0 {
+            return None;
+        }
+        let mut grid = self.grid.clone();
+        let pattern = [b"@#@", b"###", b"@#@"];
+        for (dy, row) in pattern.iter().enumerate() {
+            for (dx, c) in row.iter().enumerate() {
+                let at = (x + dx - 1, y + dy - 1);
+                if at != (x, y) && self.get(at) != b'.' {
+                    return None;
+                }
+                grid[at.1][at.0] = *c;
+            }
+        }
+        Some(Vault { grid })
+    }
+
+    /// Breadth-first from a place to every key it can reach, noting the
+    /// doors and keys along the way.
+    fn routes(&self, from: Coords) -> Vec<Route> {
+        let mut routes = Vec::new();
+        let mut seen = HashSet::new();
+        seen.insert(from);
+        let mut queue = VecDeque::from(vec![(from, 0, 0 as KeySet, 0 as KeySet)]);
+        while let Some(((x, y), distance, doors, keys)) = queue.pop_front() {
+            // Off the top or left wraps round to huge, which reads as wall
+            let around = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+            for next in around.iter() {
+                let c = self.get(*next);
+                if c == b'#' || seen.contains(next) {
+                    continue;
+                }
+                seen.insert(*next);
+                let (mut doors, mut keys) = (doors, keys);
+                if c.is_ascii_uppercase() {
+                    doors |= 1 << (c - b'A');
+                }
+                if c.is_ascii_lowercase() {
+                    let key = (c - b'a') as usize;
+                    routes.push(Route { key, distance: distance + 1, doors, keys });
+                    keys |= 1 << key;
+                }
+                queue.push_back((*next, distance + 1, doors, keys));
+            }
+        }
+        routes
+    }
+
+    /// Fewest steps for the robots to collect every key, if they can.
+    /// Works out routes between the entrances and keys first, then
+    /// runs Dijkstra over (where each robot is, keys collected).
+    pub fn shortest_collection(&self) -> Option<usize> {
+        let entrances = self.find(|c| c == b'@');
+        let keys = self.find(|c| c.is_ascii_lowercase());
+        if entrances.is_empty() {
+            return None;
+        }
+        let all_keys = keys.iter().fold(0 as KeySet, |acc, (_, c)| acc | 1 << (c - b'a'));
+
+        // Places are the entrances, then the keys (26 slots)
+        let robots = entrances.len();
+        let mut routes: Vec<Vec<Route>> = vec![Vec::new(); robots + 26];
+        for (i, (at, _)) in entrances.iter().enumerate() {
+            routes[i] = self.routes(*at);
+        }
+        for (at, c) in keys.iter() {
+            routes[robots + (c - b'a') as usize] = self.routes(*at);
+        }
+
+        let start: Vec<usize> = (0..robots).collect();
+        let mut best: HashMap<(Vec<usize>, KeySet), usize> = HashMap::new();
+        best.insert((start.clone(), 0), 0);
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, start, 0 as KeySet)));
+        while let Some(Reverse((steps, places, collected))) = queue.pop() {
+            if collected == all_keys {
+                return Some(steps);
+            }
+            if best.get(&(places.clone(), collected)).is_some_and(|b| *b < steps) {
+                continue;
+            }
+            for (robot, place) in places.iter().enumerate() {
+                for route in routes[*place].iter() {
+                    let bit = 1 << route.key;
+                    // Only go for new keys, through open doors, and
+                    // straight to the first new key on the way
+                    if collected & bit != 0
+                        || route.doors & !collected != 0
+                        || route.keys & !collected != 0 {
+                        continue;
+                    }
+                    let mut next_places = places.clone();
+                    next_places[robot] = robots + route.key;
+                    let next = (next_places, collected | bit);
+                    let next_steps = steps + route.distance;
+                    if best.get(&next).is_none_or(|b| next_steps < *b) {
+                        best.insert(next.clone(), next_steps);
+                        queue.push(Reverse((next_steps, next.0, next.1)));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn test_shortest_collection() {
+    let tests = [
+        ("#########\n#b.A.@.a#\n#########", 8),
+        ("########################\n\
+          #f.D.E.e.C.b.A.@.a.B.c.#\n\
+          ######################.#\n\
+          #d.....................#\n\
+          ########################", 86),
+        ("########################\n\
+          #...............b.C.D.f#\n\
+          #.######################\n\
+          #.....@.a.B.c.d.A.e.F.g#\n\
+          ########################", 132),
+        ("#################\n\
+          #i.G..c...e..H.p#\n\
+          ########.########\n\
+          #j.A..b...f..D.o#\n\
+          ########@########\n\
+          #k.E..a...g..B.n#\n\
+          ########.########\n\
+          #l.F..d...h..C.m#\n\
+          #################", 136),
+        ("########################\n\
+          #@..............ac.GI.b#\n\
+          ###d#e#f################\n\
+          ###A#B#C################\n\
+          ###g#h#i################\n\
+          ########################", 81),
+    ];
+    for (map, expected) in tests.iter() {
+        assert_eq!(Vault::from(*map).shortest_collection(), Some(*expected));
+    }
+    // A key behind its own door can't be had
+    assert_eq!(Vault::from("#####\n#@Aa#\n#####").shortest_collection(), None);
+}
+
+#[test]
+fn test_split() {
+    let vault = Vault::from("#######\n#a.#Cd#\n##...##\n##.@.##\n##...##\n#cB#Ab#\n#######");
+    let split = vault.split().unwrap();
+    assert_eq!(split.grid[2..5].to_vec(), vec![
+        b"##@#@##".to_vec(),
+        b"#######".to_vec(),
+        b"##@#@##".to_vec(),
+    ]);
+    assert_eq!(split.shortest_collection(), Some(8));
+    assert!(split.split().is_none());
+
+    let tests = [
+        ("###############\n\
+          #d.ABC.#.....a#\n\
+          ######@#@######\n\
+          ###############\n\
+          ######@#@######\n\
+          #b.....#.....c#\n\
+          ###############", 24),
+        ("#############\n\
+          #DcBa.#.GhKl#\n\
+          #.###@#@#I###\n\
+          #e#d#####j#k#\n\
+          ###C#@#@###J#\n\
+          #fEbA.#.FgHi#\n\
+          #############", 32),
+        ("#############\n\
+          #g#f.D#..h#l#\n\
+          #F###e#E###.#\n\
+          #dCba@#@BcIJ#\n\
+          #############\n\
+          #nK.L@#@G...#\n\
+          #M###N#H###.#\n\
+          #o#m..#i#jk.#\n\
+          #############", 72),
+    ];
+    for (map, expected) in tests.iter() {
+        assert_eq!(Vault::from(*map).shortest_collection(), Some(*expected));
+    }
+}
+
+pub fn run() {
+    let vault = Vault::from(data_file_to_string("18", "input").unwrap().as_str());
+    println!("{:?}", vault.shortest_collection());
+    match vault.split() {
+        Some(split) => println!("{:?}", split.shortest_collection()),
+        None => println!("Can't split the vault"),
+    }
+}
@@ -0,0 +1,138 @@
+use crate::common::*;
+use crate::intcode::{Intcode,Word,parse_program};
+
+/// How far down to look for a square before giving up.
+const MAX_ROWS: usize = 10_000;
+
+/// Steepest left edge (x over y) to look for before the beam's been seen.
+const MAX_SLOPE: usize = 10;
+
+/// How many rows one after another can miss the beam before giving up.
+const MAX_MISSES: usize = 100;
+
+/// The drone system. Each query needs the program started afresh, so
+/// keep an unstarted machine to clone.
+pub struct Beam {
+    drone: Intcode,
+}
+
+impl Beam {
+    pub fn new(program: Vec<Word>) -> Self {
+        Beam { drone: Intcode::new(program) }
+    }
+
+    /// Is the point pulled by the beam?
+    pub fn probe(&self, x: usize, y: usize) -> bool {
+        let mut drone = self.drone.clone();
+        drone.input(x as Word);
+        drone.input(y as Word);
+        match drone.run_with_inputs(&[]).as_slice() {
+            [0] => false,
+            [1] => true,
+            other => panic!("Drone at {},{} said {:?}", x, y, other),
+        }
+    }
+}
+
+/// How many points in the `size` by `size` area nearest the emitter
+/// does the beam pull?
+pub fn affected(size: usize, probe: impl Fn(usize, usize) -> bool) -> usize {
+    (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .filter(|(x, y)| probe(*x, *y))
+        .count()
+}
+
+/// Top-left corner of the first `size` by `size` square that fits in
+/// the beam, going down row by row.
+/// Follows the left edge of the beam down, and for each row checks if
+/// the square's top-right corner, back up the rows, is in the beam too.
+/// The beam's a cone from the emitter, so once an edge has been seen
+/// the next can't be left of it, or right of the line out through it.
+/// Rows near the emitter can miss the beam altogether, and it gives up
+/// if too many do.
+pub fn closest_square(size: usize, probe: impl Fn(usize, usize) -> bool) -> Option<(usize, usize)> {
+    if size == 0 {
+        return Some((0, 0));
+    }
+    let mut last_edge: Option<(usize, usize)> = None;
+    let mut misses = 0;
+    for y in (size - 1)..MAX_ROWS {
+        let (from, to) = match last_edge {
+            Some((x, edge_y)) => (x, (x * y).div_ceil(edge_y.max(1)) + 1),
+            None => (0, MAX_SLOPE * (y + 1)),
+        };
+        match (from..=to).find(|x| probe(*x, y)) {
+            Some(x) => {
+                misses = 0;
+                last_edge = Some((x, y));
+                let top = y + 1 - size;
+                if probe(x + size - 1, top) {
+                    return Some((x, top));
+                }
+            },
+            None => {
+                misses += 1;
+                if misses > MAX_MISSES {
+                    return None;
+                }
+            },
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+fn cone(x: usize, y: usize) -> bool {
+    // Between slopes of 0.6 and 0.8, missing the rows near the top
+    x * 5 >= y * 3 && x * 5 <= y * 4
+}
+
+#[cfg(test)]
+fn steep(x: usize, y: usize) -> bool {
+    // Between slopes of 1.2 and 1.5, so the edge is right of the diagonal
+    x * 5 >= y * 6 && x * 2 <= y * 3
+}
+
+#[test]
+fn test_closest_square() {
+    // Check everything up to a limit instead
+    let brute = |size: usize, beam: fn(usize, usize) -> bool| {
+        (0..300)
+            .flat_map(|y| (0..300).map(move |x| (x, y)))
+            .find(|(x, y)| beam(*x, *y)
+                  && beam(x + size - 1, *y)
+                  && beam(*x, y + size - 1)
+                  && beam(x + size - 1, y + size - 1))
+    };
+    for size in [1, 2, 3, 5, 10, 20] {
+        assert_eq!(closest_square(size, cone), brute(size, cone), "size {}", size);
+        assert_eq!(closest_square(size, steep), brute(size, steep), "size {}", size);
+    }
+    assert_eq!(closest_square(100, steep), Some((990, 726)));
+    assert_eq!(affected(10, cone), 11);
+    // No beam, or one too thin for anything bigger than a point
+    assert_eq!(closest_square(2, |_, _| false), None);
+    assert_eq!(closest_square(2, |x, y| x == y), None);
+}
+
+#[test]
+fn test_probe() {
+    // A beam that's everything on or below the diagonal
+    let beam = Beam::new(vec![3,100, 3,101, 7,101,100,102, 1002,102,-1,102, 1001,102,1,102, 4,102, 99]);
+    assert!(beam.probe(0, 0));
+    assert!(beam.probe(3, 7));
+    assert!(!beam.probe(7, 3));
+    assert_eq!(affected(4, |x, y| beam.probe(x, y)), 10);
+    assert_eq!(closest_square(3, |x, y| beam.probe(x, y)), Some((0, 2)));
+}
+
+pub fn run() {
+    let beam = Beam::new(parse_program(&data_file_to_string("19", "input").unwrap()));
+    let probe = |x, y| beam.probe(x, y);
+    println!("{}", affected(50, probe));
+    match closest_square(100, probe) {
+        Some((x, y)) => println!("{}", x * 10000 + y),
+        None => println!("No room for the ship in the first {} rows", MAX_ROWS),
+    }
+}
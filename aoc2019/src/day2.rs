@@ -1,34 +1,200 @@
 use crate::common::*;
-use crate::intcode::Intcode;
-
-const TARGET: isize = 19690720;
-
-pub fn run() {
-    let original = 
-        data_file_to_string("2", "input").unwrap()
-            .split(',')
-            .map(|i| i.trim())
-            .filter(|i| i.len() > 0)
-            .map(|i| i.parse::<isize>().unwrap())
-            .collect::<Vec<isize>>();
-    let mut tape: Vec<isize>;
-    let mut result: isize;
-
-    // We don't know if the program is self-modifying
-    // so just brute-force it!
-    'search: for noun in 0..100 {
-        for verb in 0..100 {
-            tape = original.clone();
-            tape[1] = noun;
-            tape[2] = verb;
-            let mut runner = Intcode::new(&mut tape);
-            runner.run(0);
-            result = tape[0];
-            if result == TARGET {
-                println!("{}", 100 * noun + verb);
-                break 'search;
-            }
+use crate::intcode::{Intcode,Word,parse_program};
+
+const TARGET: Word = 19690720;
+
+/// A value in terms of the noun and verb:
+/// `noun * self.noun + verb * self.verb + self.constant`
+#[derive(Debug,Clone,Copy,PartialEq)]
+struct Linear {
+    noun: Word,
+    verb: Word,
+    constant: Word,
+}
+
+impl Linear {
+    fn constant(constant: Word) -> Self {
+        Linear { noun: 0, verb: 0, constant }
+    }
+
+    fn as_constant(&self) -> Option<Word> {
+        if self.noun == 0 && self.verb == 0 { Some(self.constant) } else { None }
+    }
+
+    fn add(&self, other: &Linear) -> Option<Linear> {
+        Some(Linear {
+            noun: self.noun.checked_add(other.noun)?,
+            verb: self.verb.checked_add(other.verb)?,
+            constant: self.constant.checked_add(other.constant)?,
+        })
+    }
+
+    /// Only linear if one side is a plain number
+    fn mul(&self, other: &Linear) -> Option<Linear> {
+        let (scale, value) = match (self.as_constant(), other.as_constant()) {
+            (Some(k), _) => (k, other),
+            (_, Some(k)) => (k, self),
+            _ => return None,
+        };
+        Some(Linear {
+            noun: value.noun.checked_mul(scale)?,
+            verb: value.verb.checked_mul(scale)?,
+            constant: value.constant.checked_mul(scale)?,
+        })
+    }
+
+    fn eval(&self, noun: Word, verb: Word) -> Word {
+        self.noun * noun + self.verb * verb + self.constant
+    }
+}
+
+/// What we know about a memory cell while running symbolically.
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Value {
+    Known(Linear),
+    // Read through an address that depends on the noun or verb,
+    // or not linear; fine as long as it never matters
+    Unknown,
+}
+
+impl Value {
+    fn address(&self, tape: &[Value]) -> Option<usize> {
+        match self {
+            Value::Known(l) => {
+                let addr = l.as_constant()?;
+                if addr >= 0 && (addr as usize) < tape.len() { Some(addr as usize) } else { None }
+            },
+            Value::Unknown => None,
         }
     }
-    println!("Finished");
-}
\ No newline at end of file
+}
+
+/// Run the program with the noun and verb left as symbols and get
+/// address 0 as a linear expression of them.
+/// Gives up (None) if the program jumps, writes through an address that
+/// depends on them, or multiplies them together.
+fn symbolic(original: &[Word]) -> Option<Linear> {
+    let mut tape = original
+        .iter()
+        .map(|v| Value::Known(Linear::constant(*v)))
+        .collect::<Vec<Value>>();
+    if tape.len() < 3 {
+        return None;
+    }
+    tape[1] = Value::Known(Linear { noun: 1, verb: 0, constant: 0 });
+    tape[2] = Value::Known(Linear { noun: 0, verb: 1, constant: 0 });
+
+    let mut pointer = 0;
+    loop {
+        let opcode = match tape.get(pointer)? {
+            Value::Known(l) => l.as_constant()?,
+            Value::Unknown => return None,
+        };
+        if opcode == 99 {
+            break;
+        }
+        if pointer + 3 >= tape.len() {
+            return None;
+        }
+        let read = |v: &Value| match v.address(&tape) {
+            Some(addr) => tape[addr],
+            None => Value::Unknown,
+        };
+        let operand_1 = read(&tape[pointer + 1]);
+        let operand_2 = read(&tape[pointer + 2]);
+        let result_pos = tape[pointer + 3].address(&tape)?;
+        let value = match (operand_1, operand_2) {
+            (Value::Known(a), Value::Known(b)) => {
+                let value = match opcode {
+                    1 => a.add(&b),   // ADD
+                    2 => a.mul(&b),   // MUL
+                    _ => return None,
+                };
+                value.map_or(Value::Unknown, Value::Known)
+            },
+            _ if opcode == 1 || opcode == 2 => Value::Unknown,
+            _ => return None,
+        };
+        tape[result_pos] = value;
+        pointer += 4;
+    }
+    match tape[0] {
+        Value::Known(l) => Some(l),
+        Value::Unknown => None,
+    }
+}
+
+/// Run the program for real with a noun and verb, and get address 0.
+pub fn run_with(original: &[Word], noun: Word, verb: Word) -> Word {
+    let mut tape = original.to_vec();
+    tape[1] = noun;
+    tape[2] = verb;
+    let mut runner = Intcode::new(tape);
+    runner.run(0);
+    runner.tape()[0]
+}
+
+/// Find the noun and verb (each 0 to 99) that make the program leave
+/// `target` at address 0.
+pub fn find_inputs(original: &[Word], target: Word) -> Option<(Word, Word)> {
+    match symbolic(original) {
+        Some(linear) => {
+            // Solve noun * a + verb * b + c = target, trying each noun
+            (0..100).find_map(|noun| {
+                let rest = target
+                    .checked_sub(linear.constant)?
+                    .checked_sub(linear.noun.checked_mul(noun)?)?;
+                let verb = match linear.verb {
+                    0 if rest == 0 => 0,
+                    0 => return None,
+                    b if rest % b == 0 => rest / b,
+                    _ => return None,
+                };
+                if (0..100).contains(&verb) && linear.eval(noun, verb) == target {
+                    Some((noun, verb))
+                }
+                else {
+                    None
+                }
+            })
+        },
+        None => {
+            // We don't know what the program does
+            // so just brute-force it!
+            (0..100)
+                .flat_map(|noun| (0..100).map(move |verb| (noun, verb)))
+                .find(|(noun, verb)| run_with(original, *noun, *verb) == target)
+        },
+    }
+}
+
+#[test]
+fn test_symbolic() {
+    let original = parse_program(&data_file_to_string("2", "input").unwrap());
+    let linear = symbolic(&original).unwrap();
+    for (noun, verb) in [(12, 2), (0, 0), (99, 99), (37, 61)].iter() {
+        assert_eq!(linear.eval(*noun, *verb), run_with(&original, *noun, *verb));
+    }
+    let (noun, verb) = find_inputs(&original, TARGET).unwrap();
+    assert_eq!(run_with(&original, noun, verb), TARGET);
+
+    // Multiplying the noun by the verb isn't linear, so it's brute-forced
+    let mut original = vec![1,0,0,0, 2,1,2,0, 99];
+    original.resize(100, 0);
+    assert_eq!(symbolic(&original), None);
+    assert_eq!(find_inputs(&original, 12), Some((1, 12)));
+    assert_eq!(find_inputs(&original, 10007), None);
+}
+
+/// Part 1 (the 1202 program alarm) and part 2 (100 * noun + verb).
+pub fn run() -> (Word, Option<Word>) {
+    let original = parse_program(&data_file_to_string("2", "input").unwrap());
+    let part1 = run_with(&original, 12, 2);
+    println!("{}", part1);
+    let part2 = find_inputs(&original, TARGET).map(|(noun, verb)| 100 * noun + verb);
+    match part2 {
+        Some(answer) => println!("{}", answer),
+        None => println!("No noun and verb give {}", TARGET),
+    }
+    (part1, part2)
+}
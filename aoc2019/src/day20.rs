@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap,HashMap,HashSet,VecDeque};
+use std::str::FromStr;
+
+use crate::common::*;
+
+type Coords = (usize, usize);
+
+const START: &str = "AA";
+const END: &str = "ZZ";
+
+/// How deep the recursive maze gets searched from `run`.
+const MAX_DEPTH: usize = 1000;
+
+/// Problems reading a maze.
+#[derive(Debug,PartialEq)]
+pub enum ParseError {
+    /// There's no `AA` or `ZZ` (or more than one)
+    Missing(String),
+    /// A label that isn't on exactly two openings
+    Unpaired(String),
+}
+
+/// Why there's no way from AA to ZZ.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum NoPath {
+    /// There isn't one at all
+    Unreachable,
+    /// There might be, but deeper than the limit
+    DepthLimit,
+}
+
+/// Where a portal goes, and which way it changes the level.
+#[derive(Debug,Clone,Copy,PartialEq)]
+struct Portal {
+    to: Coords,
+    /// +1 going in from the inner edge, -1 out from the outer edge
+    level: isize,
+}
+
+/// The donut maze: its open tiles and the portals between them.
+#[derive(Debug)]
+pub struct Donut {
+    open: HashSet<Coords>,
+    portals: HashMap<Coords, Portal>,
+    start: Coords,
+    end: Coords,
+}
+
+impl FromStr for Donut {
+    type Err = ParseError;
+
+    /// Labels are two capital letters next to an open tile, reading
+    /// left to right or top to bottom. Leading spaces on the lines
+    /// matter; blank lines at the start and end don't.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let grid = s
+            .trim_matches('\n')
+            .lines()
+            .map(|line| line.bytes().collect::<Vec<u8>>())
+            .collect::<Vec<Vec<u8>>>();
+        let get = |x: usize, y: usize| grid.get(y).and_then(|row| row.get(x)).copied().unwrap_or(b' ');
+
+        let mut open = HashSet::new();
+        for (y, row) in grid.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                if *c == b'.' {
+                    open.insert((x, y));
+                }
+            }
+        }
+        // The outer edge is the maze's bounding box
+        let x_min = open.iter().map(|p| p.0).min().unwrap_or(0);
+        let x_max = open.iter().map(|p| p.0).max().unwrap_or(0);
+        let y_min = open.iter().map(|p| p.1).min().unwrap_or(0);
+        let y_max = open.iter().map(|p| p.1).max().unwrap_or(0);
+
+        // Sorted so a bad maze always reports the same label
+        let mut labels: BTreeMap<String, Vec<(Coords, bool)>> = BTreeMap::new();
+        for (x, y) in open.iter().copied() {
+            // Letter next to the tile, then the one beyond, in either order
+            let sides = [
+                (get(x.wrapping_sub(1), y), get(x.wrapping_sub(2), y), true),
+                (get(x + 1, y), get(x + 2, y), false),
+                (get(x, y.wrapping_sub(1)), get(x, y.wrapping_sub(2)), true),
+                (get(x, y + 1), get(x, y + 2), false),
+            ];
+            for (near, far, before) in sides.iter() {
+                if near.is_ascii_uppercase() && far.is_ascii_uppercase() {
+                    let pair = if *before { [*far, *near] } else { [*near, *far] };
+                    let label = String::from_utf8_lossy(&pair).to_string();
+                    let outer = x == x_min || x == x_max || y == y_min || y == y_max;
+                    labels.entry(label).or_default().push(((x, y), outer));
+                }
+            }
+        }
+
+        let mut end_of = |label: &str| match labels.remove(label).as_deref() {
+            Some([(at, _)]) => Ok(*at),
+            _ => Err(ParseError::Missing(String::from(label))),
+        };
+        let start = end_of(START)?;
+        let end = end_of(END)?;
+
+        let mut portals = HashMap::new();
+        for (label, ends) in labels {
+            match ends.as_slice() {
+                [(a, a_outer), (b, b_outer)] => {
+                    let level = |outer: bool| if outer { -1 } else { 1 };
+                    portals.insert(*a, Portal { to: *b, level: level(*a_outer) });
+                    portals.insert(*b, Portal { to: *a, level: level(*b_outer) });
+                },
+                _ => return Err(ParseError::Unpaired(label)),
+            }
+        }
+        Ok(Donut { open, portals, start, end })
+    }
+}
+
+impl Donut {
+    /// Steps from AA to ZZ with every portal just a shortcut.
+    pub fn shortest_path(&self) -> Option<usize> {
+        self.search(None).ok()
+    }
+
+    /// Steps from AA to ZZ where the inner portals go down a level and
+    /// the outer ones back up. Outer portals are walls on the top level,
+    /// and ZZ only counts there. Levels deeper than `max_depth` are off
+    /// limits, so it gives up rather than going down forever, and says
+    /// if that's why it didn't find a way.
+    pub fn shortest_recursive(&self, max_depth: usize) -> Result<usize, NoPath> {
+        self.search(Some(max_depth))
+    }
+
+    /// Breadth-first over (tile, level), with levels ignored if there's
+    /// no depth limit.
+    fn search(&self, max_depth: Option<usize>) -> Result<usize, NoPath> {
+        let mut hit_limit = false;
+        let mut seen = HashSet::new();
+        seen.insert((self.start, 0));
+        let mut queue = VecDeque::from(vec![(self.start, 0, 0)]);
+        while let Some((at, level, steps)) = queue.pop_front() {
+            if at == self.end && level == 0 {
+                return Ok(steps);
+            }
+            let (x, y) = at;
+            let mut next = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)]
+                .iter()
+                .filter(|p| self.open.contains(p))
+                .map(|p| (*p, level))
+                .collect::<Vec<(Coords, usize)>>();
+            if let Some(portal) = self.portals.get(&at) {
+                match max_depth {
+                    None => next.push((portal.to, level)),
+                    Some(max) => {
+                        let to_level = level as isize + portal.level;
+                        // Outer portals on the top level are walls
+                        if to_level >= 0 && to_level as usize > max {
+                            hit_limit = true;
+                        }
+                        else if to_level >= 0 {
+                            next.push((portal.to, to_level as usize));
+                        }
+                    },
+                }
+            }
+            for state in next {
+                if seen.insert(state) {
+                    queue.push_back((state.0, state.1, steps + 1));
+                }
+            }
+        }
+        Err(if hit_limit { NoPath::DepthLimit } else { NoPath::Unreachable })
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE_1: &str = "
+         A
+         A
+  #######.#########
+  #######.........#
+  #######.#######.#
+  #######.#######.#
+  #######.#######.#
+  #####  B    ###.#
+BC...##  C    ###.#
+  ##.##       ###.#
+  ##...DE  F  ###.#
+  #####    G  ###.#
+  #########.#####.#
+DE..#######...###.#
+  #.#########.###.#
+FG..#########.....#
+  ###########.#####
+             Z
+             Z
+";
+
+#[cfg(test)]
+const EXAMPLE_3: &str = "
+             Z L X W       C
+             Z P Q B       K
+  ###########.#.#.#.#######.###############
+  #...#.......#.#.......#.#.......#.#.#...#
+  ###.#.#.#.#.#.#.#.###.#.#.#######.#.#.###
+  #.#...#.#.#...#.#.#...#...#...#.#.......#
+  #.###.#######.###.###.#.###.###.#.#######
+  #...#.......#.#...#...#.............#...#
+  #.#########.#######.#.#######.#######.###
+  #...#.#    F       R I       Z    #.#.#.#
+  #.###.#    D       E C       H    #.#.#.#
+  #.#...#                           #...#.#
+  #.###.#                           #.###.#
+  #.#....OA                       WB..#.#..ZH
+  #.###.#                           #.#.#.#
+CJ......#                           #.....#
+  #######                           #######
+  #.#....CK                         #......IC
+  #.###.#                           #.###.#
+  #.....#                           #...#.#
+  ###.###                           #.#.#.#
+XF....#.#                         RF..#.#.#
+  #####.#                           #######
+  #......CJ                       NM..#...#
+  ###.#.#                           #.###.#
+RE....#.#                           #......RF
+  ###.###        X   X       L      #.#.#.#
+  #.....#        F   Q       P      #.#.#.#
+  ###.###########.###.#######.#########.###
+  #.....#...#.....#.......#...#.....#.#...#
+  #####.#.###.#######.#######.###.###.#.#.#
+  #.......#.......#.#.#.#.#...#...#...#.#.#
+  #####.###.#####.#.#.#.#.###.###.#.###.###
+  #.......#.....#.#...#...............#...#
+  #############.#.#.###.###################
+               A O F   N
+               A A D   M
+";
+
+#[test]
+fn test_parse() {
+    let donut = EXAMPLE_1.parse::<Donut>().unwrap();
+    assert_eq!(donut.start, (9, 2));
+    assert_eq!(donut.end, (13, 16));
+    // BC, DE and FG, both ends each
+    assert_eq!(donut.portals.len(), 6);
+    assert_eq!(donut.portals[&(9, 6)], Portal { to: (2, 8), level: 1 });
+    assert_eq!(donut.portals[&(2, 8)], Portal { to: (9, 6), level: -1 });
+
+    assert_eq!("  #.#\n  #.#".parse::<Donut>().unwrap_err(),
+               ParseError::Missing(String::from(START)));
+    // Leaves one each of DE and XY
+    assert_eq!(EXAMPLE_1.replacen("DE", "XY", 1).parse::<Donut>().unwrap_err(),
+               ParseError::Unpaired(String::from("DE")));
+}
+
+#[test]
+fn test_shortest_path() {
+    let donut = EXAMPLE_1.parse::<Donut>().unwrap();
+    assert_eq!(donut.shortest_path(), Some(23));
+    assert_eq!(donut.shortest_recursive(10), Ok(26));
+    // It never needs to leave the top level
+    assert_eq!(donut.shortest_recursive(0), Ok(26));
+
+    // ZZ walled off from AA
+    let donut = "   A   Z\n   A   Z\n  #.###.#\n  #######".parse::<Donut>().unwrap();
+    assert_eq!(donut.shortest_path(), None);
+    assert_eq!(donut.shortest_recursive(10), Err(NoPath::Unreachable));
+    // Even with a portal that only goes out from the top level
+    let donut = "   A   Z\n   A   Z\n  #.###.#\nBC..#####\n  #.#####\n   B\n   C".parse::<Donut>().unwrap();
+    assert_eq!(donut.portals.len(), 2);
+    assert_eq!(donut.shortest_recursive(10), Err(NoPath::Unreachable));
+}
+
+#[test]
+fn test_shortest_recursive() {
+    let donut = EXAMPLE_3.parse::<Donut>().unwrap();
+    assert_eq!(donut.shortest_path(), Some(77));
+    assert_eq!(donut.shortest_recursive(10), Ok(396));
+    assert_eq!(donut.shortest_recursive(9), Err(NoPath::DepthLimit));
+}
+
+pub fn run() {
+    let donut = match data_file_to_string("20", "input").unwrap().parse::<Donut>() {
+        Ok(donut) => donut,
+        Err(e) => panic!("{:?}", e),
+    };
+    match donut.shortest_path() {
+        Some(steps) => println!("{}", steps),
+        None => println!("There's no way from {} to {}", START, END),
+    }
+    match donut.shortest_recursive(MAX_DEPTH) {
+        Ok(steps) => println!("{}", steps),
+        Err(NoPath::Unreachable) => println!("There's no way from {} to {}", START, END),
+        Err(NoPath::DepthLimit) => println!("No way from {} to {} within {} levels", START, END, MAX_DEPTH),
+    }
+}
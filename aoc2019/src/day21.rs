@@ -0,0 +1,269 @@
+use std::fmt;
+
+use crate::common::*;
+use crate::intcode::{Intcode,Word,parse_program};
+use Register::*;
+
+/// The droid only has room for this many instructions.
+const MAX_INSTRUCTIONS: usize = 15;
+
+/// How far ahead a jump lands.
+const JUMP: usize = 4;
+
+/// Springscript registers: the sensors A to I (ground 1 to 9 tiles
+/// ahead), then the temporary and jump registers.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Register {
+    A, B, C, D, E, F, G, H, I,
+    T,
+    J,
+}
+
+impl Register {
+    /// Which tile ahead a sensor looks at, or None for T and J.
+    fn sensor(self) -> Option<usize> {
+        match self {
+            Register::T | Register::J => None,
+            sensor => Some(sensor as usize + 1),
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Instruction {
+    And(Register, Register),
+    Or(Register, Register),
+    Not(Register, Register),
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, x, y) = match self {
+            Instruction::And(x, y) => ("AND", x, y),
+            Instruction::Or(x, y) => ("OR", x, y),
+            Instruction::Not(x, y) => ("NOT", x, y),
+        };
+        write!(f, "{} {:?} {:?}", name, x, y)
+    }
+}
+
+/// WALK can see 4 tiles ahead, RUN all 9.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    fn command(self) -> &'static str {
+        match self {
+            Mode::Walk => "WALK",
+            Mode::Run => "RUN",
+        }
+    }
+
+    fn range(self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+}
+
+/// Why a script can't be sent.
+#[derive(Debug,PartialEq)]
+pub enum ScriptError {
+    /// More instructions than the droid's memory holds
+    TooLong(usize),
+    /// Only T and J can be written
+    NotWritable(Register),
+    /// A sensor the mode can't see that far with
+    OutOfRange(Register),
+}
+
+/// Check a script would be accepted in a mode.
+pub fn check(script: &[Instruction], mode: Mode) -> Result<(), ScriptError> {
+    if script.len() > MAX_INSTRUCTIONS {
+        return Err(ScriptError::TooLong(script.len()));
+    }
+    for instruction in script {
+        let (x, y) = match instruction {
+            Instruction::And(x, y) | Instruction::Or(x, y) | Instruction::Not(x, y) => (*x, *y),
+        };
+        if y.sensor().is_some() {
+            return Err(ScriptError::NotWritable(y));
+        }
+        if x.sensor().is_some_and(|ahead| ahead > mode.range()) {
+            return Err(ScriptError::OutOfRange(x));
+        }
+    }
+    Ok(())
+}
+
+/// What happened to the droid.
+#[derive(Debug,PartialEq)]
+pub enum Outcome {
+    /// It made it across and reported the hull damage
+    Damage(Word),
+    /// It fell into space; this is the animation of its last moments
+    Fell(String),
+}
+
+/// Send a script to the droid, then WALK or RUN.
+pub fn send(program: &[Word], script: &[Instruction], mode: Mode) -> Result<Outcome, ScriptError> {
+    check(script, mode)?;
+    let mut droid = Intcode::new(program.to_vec());
+    // Skip the prompt
+    droid.read_ascii();
+    for instruction in script {
+        droid.input_line(&instruction.to_string());
+    }
+    droid.input_line(mode.command());
+    let (text, others) = droid.read_ascii();
+    Ok(match others.first() {
+        Some(damage) => Outcome::Damage(*damage),
+        None => Outcome::Fell(text),
+    })
+}
+
+/// Run a script on the sensor readings.
+fn jumps(script: &[Instruction], ground: impl Fn(usize) -> bool) -> bool {
+    let (mut t, mut j) = (false, false);
+    for instruction in script {
+        let (x, y) = match instruction {
+            Instruction::And(x, y) | Instruction::Or(x, y) | Instruction::Not(x, y) => (*x, *y),
+        };
+        let x = match x {
+            Register::T => t,
+            Register::J => j,
+            sensor => ground(sensor.sensor().unwrap()),
+        };
+        let y = if y == Register::T { &mut t } else { &mut j };
+        *y = match instruction {
+            Instruction::And(..) => x && *y,
+            Instruction::Or(..) => x || *y,
+            Instruction::Not(..) => !x,
+        };
+    }
+    j
+}
+
+/// Why a simulated droid didn't make it.
+#[derive(Debug,PartialEq)]
+pub enum SimulationError {
+    /// The droid wouldn't take the script
+    Script(ScriptError),
+    /// It fell through this tile
+    Fell(usize),
+}
+
+impl From<ScriptError> for SimulationError {
+    fn from(e: ScriptError) -> SimulationError {
+        SimulationError::Script(e)
+    }
+}
+
+/// Try a script on a stretch of hull like `#####.#..########` without
+/// the Intcode program. The droid starts on the first tile and
+/// everything past the end is ground.
+/// Scripts get checked just as `send` does.
+pub fn simulate(script: &[Instruction], mode: Mode, hull: &str) -> Result<(), SimulationError> {
+    check(script, mode)?;
+    let hull = hull.trim().as_bytes();
+    let ground = |at: usize| hull.get(at).is_none_or(|c| *c == b'#');
+    let mut at = 0;
+    while at < hull.len() {
+        if !ground(at) {
+            return Err(SimulationError::Fell(at));
+        }
+        at += if jumps(script, |n| ground(at + n)) { JUMP } else { 1 };
+    }
+    Ok(())
+}
+
+/// Jump if there's a hole in the next three tiles and ground to land on.
+pub fn walk_script() -> Vec<Instruction> {
+    vec![
+        Instruction::Not(A, J),
+        Instruction::Not(B, T),
+        Instruction::Or(T, J),
+        Instruction::Not(C, T),
+        Instruction::Or(T, J),
+        Instruction::And(D, J),
+    ]
+}
+
+/// As for walking, but only if after landing the droid can either
+/// step forward (E) or jump again straight away (H).
+pub fn run_script() -> Vec<Instruction> {
+    let mut script = walk_script();
+    script.extend(vec![
+        Instruction::Not(E, T),
+        Instruction::Not(T, T),
+        Instruction::Or(H, T),
+        Instruction::And(T, J),
+    ]);
+    script
+}
+
+#[test]
+fn test_check() {
+    assert_eq!(walk_script()[2].to_string(), "OR T J");
+    assert_eq!(check(&walk_script(), Mode::Walk), Ok(()));
+    assert_eq!(check(&run_script(), Mode::Walk), Err(ScriptError::OutOfRange(E)));
+    assert_eq!(check(&run_script(), Mode::Run), Ok(()));
+    assert_eq!(check(&[Instruction::Or(J, A)], Mode::Run), Err(ScriptError::NotWritable(A)));
+    assert_eq!(check(&[Instruction::Or(A, J); 16], Mode::Run), Err(ScriptError::TooLong(16)));
+}
+
+#[test]
+fn test_simulate() {
+    let walking = ["#####.###########", "#####...#########", "#####..#.########", "#####.#..########"];
+    for hull in walking.iter() {
+        assert_eq!(simulate(&walk_script(), Mode::Walk, hull), Ok(()), "{}", hull);
+        assert_eq!(simulate(&run_script(), Mode::Run, hull), Ok(()), "{}", hull);
+    }
+    // Not jumping at all
+    assert_eq!(simulate(&[], Mode::Walk, walking[0]), Err(SimulationError::Fell(5)));
+    // Jumping early lands the walker where it can't get out
+    let running = "#####.#.##..#####";
+    assert_eq!(simulate(&walk_script(), Mode::Walk, running), Err(SimulationError::Fell(7)));
+    assert_eq!(simulate(&run_script(), Mode::Run, running), Ok(()));
+    // Same as the droid, walking can't see far enough for this
+    assert_eq!(simulate(&run_script(), Mode::Walk, running),
+               Err(SimulationError::Script(ScriptError::OutOfRange(E))));
+}
+
+#[test]
+fn test_send() {
+    // A droid that prints its prompt, ignores the script and then
+    // reports some damage
+    let mut program = "Input instructions:\n"
+        .bytes()
+        .flat_map(|b| vec![104, Word::from(b)])
+        .collect::<Vec<Word>>();
+    program.extend(vec![3,1000, 104,19355645, 99]);
+    assert_eq!(send(&program, &walk_script(), Mode::Walk), Ok(Outcome::Damage(19355645)));
+    assert_eq!(send(&program, &run_script(), Mode::Walk), Err(ScriptError::OutOfRange(E)));
+
+    // One that falls in instead
+    let len = program.len();
+    program.truncate(len - 3);
+    program.extend("Didn't make it across:\n\n.....@.....\n".bytes().flat_map(|b| vec![104, Word::from(b)]));
+    program.push(99);
+    match send(&program, &walk_script(), Mode::Walk) {
+        Ok(Outcome::Fell(text)) => assert!(text.contains('@')),
+        other => panic!("{:?}", other),
+    }
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("21", "input").unwrap());
+    for (script, mode) in [(walk_script(), Mode::Walk), (run_script(), Mode::Run)] {
+        match send(&program, &script, mode) {
+            Ok(Outcome::Damage(damage)) => println!("{}", damage),
+            Ok(Outcome::Fell(animation)) => println!("{}", animation),
+            Err(e) => println!("{:?}", e),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::str::FromStr;
+
+use crate::common::*;
+
+/// Shuffles work modulo the deck size.
+pub use crate::common::Modulo;
+
+const PART1_DECK: u64 = 10007;
+const PART2_DECK: u64 = 119315717514047;
+const PART2_SHUFFLES: u64 = 101741582076661;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Technique {
+    NewStack,
+    Cut(i64),
+    Increment(u64),
+}
+
+impl FromStr for Technique {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn number<T: FromStr>(s: &str, n: &str) -> Result<T, String> {
+            n.trim().parse().map_err(|_| format!("Bad number in {:?}", s))
+        }
+        let s = s.trim();
+        if s == "deal into new stack" {
+            Ok(Technique::NewStack)
+        }
+        else if let Some(n) = s.strip_prefix("cut ") {
+            Ok(Technique::Cut(number(s, n)?))
+        }
+        else if let Some(n) = s.strip_prefix("deal with increment ") {
+            Ok(Technique::Increment(number(s, n)?))
+        }
+        else {
+            Err(format!("Unknown technique {:?}", s))
+        }
+    }
+}
+
+/// A shuffle as the map from where a card starts to where it ends up,
+/// `position * scale + offset` modulo the deck size. Every technique
+/// is one of these, and so is doing one after another.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Shuffle {
+    scale: i128,
+    offset: i128,
+    deck: Modulo,
+}
+
+impl Shuffle {
+    /// Leaves the deck as it is.
+    pub fn identity(deck: Modulo) -> Self {
+        Shuffle { scale: deck.reduce(1), offset: 0, deck }
+    }
+
+    /// None if it'd lose cards: an increment that isn't coprime with
+    /// the deck size.
+    pub fn technique(technique: Technique, deck: Modulo) -> Option<Self> {
+        let (scale, offset) = match technique {
+            Technique::NewStack => (-1, -1),
+            Technique::Cut(n) => (1, -i128::from(n)),
+            Technique::Increment(n) => {
+                deck.inverse(i128::from(n))?;
+                (i128::from(n), 0)
+            },
+        };
+        Some(Shuffle { scale: deck.reduce(scale), offset: deck.reduce(offset), deck })
+    }
+
+    /// All the techniques one after another.
+    pub fn new(techniques: &[Technique], deck: Modulo) -> Option<Self> {
+        techniques
+            .iter()
+            .try_fold(Self::identity(deck), |acc, t| acc.then(&Self::technique(*t, deck)?))
+    }
+
+    /// This shuffle and then another.
+    pub fn then(&self, next: &Shuffle) -> Option<Self> {
+        let deck = self.deck;
+        Some(Shuffle {
+            scale: deck.mul(next.scale, self.scale)?,
+            offset: deck.add(deck.mul(next.scale, self.offset)?, next.offset)?,
+            deck,
+        })
+    }
+
+    /// The same shuffle done `times` times over, by repeated squaring.
+    pub fn repeat(&self, mut times: u64) -> Option<Self> {
+        let mut result = Self::identity(self.deck);
+        let mut power = *self;
+        while times > 0 {
+            if times & 1 == 1 {
+                result = result.then(&power)?;
+            }
+            power = power.then(&power)?;
+            times >>= 1;
+        }
+        Some(result)
+    }
+
+    /// The shuffle that puts the deck back.
+    pub fn inverse(&self) -> Option<Self> {
+        let deck = self.deck;
+        let scale = deck.inverse(self.scale)?;
+        Some(Shuffle { scale, offset: deck.mul(deck.sub(0, self.offset)?, scale)?, deck })
+    }
+
+    /// Where the card that started at `card` ends up.
+    pub fn position_of(&self, card: u64) -> Option<u64> {
+        let deck = self.deck;
+        let position = deck.add(deck.mul(self.scale, i128::from(card))?, self.offset)?;
+        Some(position as u64)
+    }
+
+    /// Which card ends up at `position`.
+    pub fn card_at(&self, position: u64) -> Option<u64> {
+        self.inverse()?.position_of(position)
+    }
+}
+
+/// Read one technique per line.
+pub fn parse_techniques(source: &str) -> Result<Vec<Technique>, String> {
+    source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect()
+}
+
+#[test]
+fn test_examples() {
+    let tests = [
+        ("deal with increment 7\ndeal into new stack\ndeal into new stack",
+         [0, 3, 6, 9, 2, 5, 8, 1, 4, 7]),
+        ("cut 6\ndeal with increment 7\ndeal into new stack",
+         [3, 0, 7, 4, 1, 8, 5, 2, 9, 6]),
+        ("deal with increment 7\ndeal with increment 9\ncut -2",
+         [6, 3, 0, 7, 4, 1, 8, 5, 2, 9]),
+        ("deal into new stack\ncut -2\ndeal with increment 7\ncut 8\ncut -4\n\
+          deal with increment 7\ncut 3\ndeal with increment 9\ndeal with increment 3\ncut -1",
+         [9, 2, 5, 8, 1, 4, 7, 0, 3, 6]),
+    ];
+    let deck = Modulo::new(10).unwrap();
+    for (source, expected) in tests.iter() {
+        let shuffle = Shuffle::new(&parse_techniques(source).unwrap(), deck).unwrap();
+        let cards = (0..10).map(|p| shuffle.card_at(p).unwrap()).collect::<Vec<u64>>();
+        assert_eq!(cards, expected.to_vec());
+        for (position, card) in expected.iter().enumerate() {
+            assert_eq!(shuffle.position_of(*card), Some(position as u64));
+        }
+    }
+    assert_eq!(Shuffle::technique(Technique::Increment(4), deck), None);
+    assert!(parse_techniques("cut 3\nriffle").is_err());
+    assert!(parse_techniques("cut three").is_err());
+}
+
+#[test]
+fn test_repeat() {
+    let techniques = parse_techniques("deal with increment 7\ncut -2\ndeal into new stack\ncut 8467").unwrap();
+    let deck = Modulo::new(PART1_DECK).unwrap();
+    let shuffle = Shuffle::new(&techniques, deck).unwrap();
+    let mut slow = Shuffle::identity(deck);
+    for times in 0..20 {
+        assert_eq!(shuffle.repeat(times), Some(slow));
+        slow = slow.then(&shuffle).unwrap();
+    }
+    let back = shuffle.repeat(1234).unwrap().then(&shuffle.repeat(1234).unwrap().inverse().unwrap());
+    assert_eq!(back, Some(Shuffle::identity(deck)));
+
+    // The huge deck and shuffle count fit in the arithmetic
+    let (_, card) = solve(&techniques).unwrap();
+    let deck = Modulo::new(PART2_DECK).unwrap();
+    let shuffle = Shuffle::new(&techniques, deck).unwrap().repeat(PART2_SHUFFLES).unwrap();
+    assert_eq!(shuffle.position_of(card), Some(2020));
+}
+
+/// Where card 2019 ends up (part 1), and which card ends at 2020 after
+/// shuffling the huge deck over and over (part 2).
+pub fn solve(techniques: &[Technique]) -> Option<(u64, u64)> {
+    let part1 = Shuffle::new(techniques, Modulo::new(PART1_DECK)?)?.position_of(2019)?;
+    let part2 = Shuffle::new(techniques, Modulo::new(PART2_DECK)?)?
+        .repeat(PART2_SHUFFLES)?
+        .card_at(2020)?;
+    Some((part1, part2))
+}
+
+pub fn run() {
+    let techniques = match parse_techniques(&data_file_to_string("22", "input").unwrap()) {
+        Ok(techniques) => techniques,
+        Err(e) => panic!("{}", e),
+    };
+    match solve(&techniques) {
+        Some((part1, part2)) => {
+            println!("{}", part1);
+            println!("{}", part2);
+        },
+        None => println!("An increment doesn't fit the deck size"),
+    }
+}
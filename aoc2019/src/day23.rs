@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use crate::common::*;
+use crate::intcode::{Event,Intcode,Word,parse_program};
+
+const COMPUTERS: usize = 50;
+
+/// Packets sent here go to the NAT rather than a computer.
+pub const NAT: usize = 255;
+
+/// What a computer reads when it has no packets waiting.
+const NO_PACKET: Word = -1;
+
+/// Intcode computers that talk to each other with `(dest, x, y)`
+/// packets, plus the NAT that wakes them up when they all go quiet.
+pub struct Network {
+    computers: Vec<Intcode>,
+    /// Values waiting to be read by each computer
+    queues: Vec<VecDeque<Word>>,
+    /// What each computer has output of a packet it hasn't finished
+    /// sending; it might stop for input halfway through
+    sending: Vec<Vec<Word>>,
+    /// The last packet sent to the NAT
+    nat: Option<(Word, Word)>,
+    /// The last y the NAT sent to computer 0
+    last_wake: Option<Word>,
+}
+
+/// What happened in a round.
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Round {
+    Busy,
+    /// Nobody had anything to read or sent anything
+    Idle,
+    /// Every computer has exited
+    Halted,
+}
+
+impl Network {
+    /// Boot `size` computers, each told its address first.
+    pub fn new(program: &[Word], size: usize) -> Self {
+        let computers = vec![Intcode::new(program.to_vec()); size];
+        let queues = (0..size).map(|a| VecDeque::from(vec![a as Word])).collect();
+        Network { computers, queues, sending: vec![Vec::new(); size], nat: None, last_wake: None }
+    }
+
+    /// Give each computer its queued values (or -1 if there are none),
+    /// run it until it wants more, and route what it sent.
+    /// Packets to addresses that don't exist are dropped.
+    fn round(&mut self) -> Round {
+        let mut idle = true;
+        let mut halted = true;
+        for address in 0..self.computers.len() {
+            let computer = &mut self.computers[address];
+            if self.queues[address].is_empty() {
+                computer.input(NO_PACKET);
+            }
+            else {
+                idle = false;
+                self.queues[address].drain(..).for_each(|v| computer.input(v));
+            }
+            let sent = &mut self.sending[address];
+            loop {
+                match computer.resume() {
+                    Event::Output(value) => {
+                        idle = false;
+                        sent.push(value);
+                    },
+                    Event::NeedInput => {
+                        halted = false;
+                        break;
+                    },
+                    Event::Halted => break,
+                }
+            }
+            // Anything after the last whole packet waits for the rest
+            let whole = sent.len() - sent.len() % 3;
+            let packets = sent.drain(..whole).collect::<Vec<Word>>();
+            for packet in packets.chunks(3) {
+                let (dest, x, y) = (packet[0], packet[1], packet[2]);
+                if dest == NAT as Word {
+                    self.nat = Some((x, y));
+                }
+                else if let Some(queue) = usize::try_from(dest).ok().and_then(|d| self.queues.get_mut(d)) {
+                    queue.extend([x, y]);
+                }
+            }
+        }
+        if halted { Round::Halted } else if idle { Round::Idle } else { Round::Busy }
+    }
+
+    /// Run until something's sent to the NAT, and get its y (part 1).
+    /// None if the network stops without that happening.
+    pub fn first_nat_y(&mut self) -> Option<Word> {
+        while self.nat.is_none() {
+            if self.round() != Round::Busy {
+                return None;
+            }
+        }
+        self.nat.map(|(_, y)| y)
+    }
+
+    /// Run with the NAT sending its last packet to computer 0 whenever
+    /// the network goes idle, until it sends the same y twice in a
+    /// row, and get that y (part 2).
+    /// None if the network goes idle with nothing for the NAT to send,
+    /// or every computer exits.
+    pub fn first_repeated_wake(&mut self) -> Option<Word> {
+        loop {
+            match self.round() {
+                Round::Busy => {},
+                Round::Halted => return None,
+                Round::Idle => {
+                    let (x, y) = self.nat?;
+                    if self.last_wake == Some(y) {
+                        return Some(y);
+                    }
+                    self.last_wake = Some(y);
+                    self.queues[0].extend([x, y]);
+                },
+            }
+        }
+    }
+}
+
+/// Three computers in a ring. Computer 0 starts things off by sending
+/// (0, 10) to 1; each passes on what it gets to the next address with y
+/// one lower (down to 5), and the last sends to the NAT.
+#[cfg(test)]
+const RING: [Word; 58] = [
+    3,100,                  // read address
+    1005,100,11,            // only computer 0 sends first
+    104,1, 104,0, 104,10,
+    3,101,                  // read x, again if -1
+    1008,101,-1,102,
+    1005,102,11,
+    3,103,                  // read y and bring it down
+    107,5,103,106,
+    1002,106,-1,106,
+    1,103,106,103,
+    1001,100,1,104,         // the next address, or the NAT
+    1007,104,3,105,
+    1005,105,49,
+    1101,0,255,104,
+    4,104, 4,101, 4,103,    // send it on
+    1105,1,11,
+];
+
+#[test]
+fn test_network() {
+    assert_eq!(Network::new(&RING, 3).first_nat_y(), Some(8));
+    // The NAT sends 8, then 5 twice
+    let mut network = Network::new(&RING, 3);
+    assert_eq!(network.first_repeated_wake(), Some(5));
+    assert_eq!(network.nat, Some((0, 5)));
+
+    // Without the last computer nothing gets to the NAT
+    assert_eq!(Network::new(&RING, 2).first_nat_y(), None);
+    assert_eq!(Network::new(&RING, 2).first_repeated_wake(), None);
+    // Computers that stop for input halfway through sending
+    let mut slow = RING.to_vec();
+    slow.splice(53..53, vec![3,107]);
+    assert_eq!(Network::new(&slow, 3).first_nat_y(), Some(8));
+    assert_eq!(Network::new(&slow, 3).first_repeated_wake(), Some(5));
+
+    // Computers that just exit
+    assert_eq!(Network::new(&[99], 3).first_repeated_wake(), None);
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("23", "input").unwrap());
+    println!("{:?}", Network::new(&program, COMPUTERS).first_nat_y());
+    println!("{:?}", Network::new(&program, COMPUTERS).first_repeated_wake());
+}
@@ -1,8 +1,12 @@
 use std::cmp::{min,max};
+use std::collections::{BTreeSet,HashMap};
 
 use crate::common::*;
 
-type Coords = (isize, isize);
+pub type Coords = (isize, isize);
+
+/// Where all the wires start; doesn't count as a crossing.
+pub const ORIGIN: Coords = (0, 0);
 
 #[derive(PartialEq,Debug,Clone)]
 enum Orientation {
@@ -11,7 +15,7 @@ enum Orientation {
 }
 
 #[derive(PartialEq,Debug,Clone)]
-struct Line {
+pub struct Line {
     x1: isize,
     y1: isize,
     x2: isize,
@@ -42,12 +46,26 @@ impl Line {
         (a <= mid && b >= mid) || (b <= mid && a >= mid)
     }
 
-    /// Get the intercept point of this line and another
-    /// (if any)
-    fn intercept_point(&self, other: &Line) -> Option<Coords> {
+    /// Get the points this line shares with another, if any.
+    /// Crossing lines share one point, but lines running along
+    /// the same row or column share every point where they overlap.
+    fn intercept_points(&self, other: &Line) -> Vec<Coords> {
         if self.orientation == other.orientation {
-            // Lines are parallel
-            None
+            let overlap = |a1: isize, a2: isize, b1: isize, b2: isize| {
+                max(min(a1, a2), min(b1, b2))..=min(max(a1, a2), max(b1, b2))
+            };
+            match self.orientation {
+                Orientation::Horizontal if self.y1 == other.y1 =>
+                    overlap(self.x1, self.x2, other.x1, other.x2)
+                        .map(|x| (x, self.y1))
+                        .collect(),
+                Orientation::Vertical if self.x1 == other.x1 =>
+                    overlap(self.y1, self.y2, other.y1, other.y2)
+                        .map(|y| (self.x1, y))
+                        .collect(),
+                // Lines are parallel
+                _ => Vec::new(),
+            }
         }
         else {
             let (horiz, vert) =
@@ -59,14 +77,31 @@ impl Line {
                 };
             if Line::between(vert.x1, horiz.x1, horiz.x2)
             && Line::between(horiz.y1, vert.y1, vert.y2) {
-                Some((vert.x1, horiz.y1))
+                vec![(vert.x1, horiz.y1)]
             }
             else {
-                None
+                Vec::new()
             }
         }
     }
 
+    /// The row (horizontal) or column (vertical) the line runs along.
+    fn along(&self) -> isize {
+        match self.orientation {
+            Orientation::Horizontal => self.y1,
+            Orientation::Vertical => self.x1,
+        }
+    }
+
+    /// The lowest and highest positions the line covers along its row
+    /// or column.
+    fn span(&self) -> (isize, isize) {
+        match self.orientation {
+            Orientation::Horizontal => (min(self.x1, self.x2), max(self.x1, self.x2)),
+            Orientation::Vertical => (min(self.y1, self.y2), max(self.y1, self.y2)),
+        }
+    }
+
     /// Get the number of steps in the line.
     fn blocks(&self) -> usize {
         ((max(self.x1, self.x2) - min(self.x1,self.x2)) +
@@ -92,6 +127,31 @@ fn test_len() {
 }
 
 
+#[test]
+fn test_intercept_points() {
+    let tests = [
+        // crossing
+        ((0,2,4,2), (1,0,1,5), vec![(1,2)]),
+        ((0,2,4,2), (5,0,5,5), vec![]),
+        // touching at the ends
+        ((0,2,4,2), (4,2,4,5), vec![(4,2)]),
+        // overlapping along a row or column
+        ((0,2,4,2), (6,2,3,2), vec![(3,2),(4,2)]),
+        ((1,0,1,3), (1,5,1,-1), vec![(1,0),(1,1),(1,2),(1,3)]),
+        ((0,2,4,2), (0,3,4,3), vec![]),
+        // zero-length moves are points
+        ((0,2,4,2), (2,2,2,2), vec![(2,2)]),
+        ((1,0,1,3), (1,3,1,3), vec![(1,3)]),
+    ];
+    for (a, b, expected) in tests.iter() {
+        let a = Line::new(a.0, a.1, a.2, a.3);
+        let b = Line::new(b.0, b.1, b.2, b.3);
+        assert_eq!(&a.intercept_points(&b), expected);
+        assert_eq!(&b.intercept_points(&a), expected);
+    }
+}
+
+
 /// Convert string to coordinate change
 fn spec_to_vec(spec: &str) -> Coords {
     let (direction, value) = (&spec[0..1], &spec[1..]);
@@ -110,28 +170,28 @@ fn spec_to_vec(spec: &str) -> Coords {
 fn row_moves(source: &str) -> Vec<Coords> {
     let rows_of_moves:Vec<Coords> = 
         source
-            .split(",")              // break row on commas
-            .map(|i| spec_to_vec(i)) // convert coordinate deltas
+            .split(',')              // break row on commas
+            .map(spec_to_vec)        // convert coordinate deltas
             .collect();
     rows_of_moves
 }
 
 
 /// Load rows of moves
-fn rows_of_moves(source: String) -> Vec<Vec<Coords>> {
+pub fn rows_of_moves(source: String) -> Vec<Vec<Coords>> {
     let rows_of_moves:Vec<Vec<Coords>> = 
         source
-            .split("\n")                  // split into rows
+            .split('\n')                  // split into rows
             .map(|row| row.trim())        // trim excess whitespace
-            .filter(|row| row.len() > 0)  // exclude empty lines
-            .map(|row| row_moves(row))
+            .filter(|row| !row.is_empty())  // exclude empty lines
+            .map(row_moves)
             .collect();
     rows_of_moves
 }
 
 
 /// Turn moves into lines
-fn moves_to_lines(moves: &Vec<Coords>) -> Vec<Line> {
+pub fn moves_to_lines(moves: &[Coords]) -> Vec<Line> {
     moves
         .iter()
         .scan( (0, 0, 0, 0), |curr, (x, y)| {
@@ -145,7 +205,7 @@ fn moves_to_lines(moves: &Vec<Coords>) -> Vec<Line> {
 
 
 /// Turn rows of moves to rows of lines
-fn rows_of_lines(rows_of_moves: &Vec<Vec<Coords>>) -> Vec<Vec<Line>> {
+pub fn rows_of_lines(rows_of_moves: &[Vec<Coords>]) -> Vec<Vec<Line>> {
     rows_of_moves
         .iter()
         .map(|row| moves_to_lines(row))
@@ -153,22 +213,198 @@ fn rows_of_lines(rows_of_moves: &Vec<Vec<Coords>>) -> Vec<Vec<Line>> {
 }
 
 
-/// Get the crossing points from a bunch of lines
-/// Gets the coords and the number of lines taken to the crossing on both paths.
-fn crossings(path_a: &Vec<Line>, path_b: &Vec<Line>) -> Vec<(Coords, usize, usize)> {
-    let mut crossings: Vec<(Coords, usize, usize)> = Vec::new();
-    for (i_a, line_a) in path_a.iter().enumerate() {
-        for (i_b, line_b) in path_b.iter().enumerate() {
-            if let Some(intercept) = line_a.intercept_point(line_b) {
-                crossings.push((intercept, i_a, i_b));
-                // println!("({}, {}):{}, {}", intercept.0, intercept.1, i_a, i_b);
+/// Where two different wires cross, and the steps taken along
+/// each wire to get there.
+#[derive(PartialEq,Debug,Clone)]
+pub struct Crossing {
+    pub at: Coords,
+    /// Indexes of the two wires, lower first
+    pub wires: (usize, usize),
+    /// Steps along each of those wires
+    pub steps: (usize, usize),
+}
+
+/// A line on a wire, with the steps taken along the wire to its start.
+struct Segment<'a> {
+    wire: usize,
+    line: &'a Line,
+    steps: usize,
+}
+
+impl Segment<'_> {
+    /// Steps along the wire to a point on this segment.
+    fn steps_to(&self, point: Coords) -> usize {
+        self.steps
+            + ((point.0 - self.line.x1).abs() + (point.1 - self.line.y1).abs())
+            as usize
+    }
+}
+
+/// Keep the fewest steps along each wire to each crossing, so a wire
+/// that loops back over a crossing counts its first visit.
+fn record(found: &mut HashMap<(Coords, usize, usize), (usize, usize)>,
+          at: Coords, a: &Segment, b: &Segment) {
+    if at == ORIGIN || a.wire == b.wire {
+        return;
+    }
+    let (first, second) = if a.wire < b.wire { (a, b) } else { (b, a) };
+    let steps = (first.steps_to(at), second.steps_to(at));
+    found
+        .entry((at, first.wire, second.wire))
+        .and_modify(|s| *s = (min(s.0, steps.0), min(s.1, steps.1)))
+        .or_insert(steps);
+}
+
+/// Get the crossing points between any two of a bunch of wires,
+/// apart from the origin.
+/// Sweeps a vertical line across from left to right keeping the
+/// horizontal lines it's touching sorted by y, so each vertical line
+/// only looks at the horizontals it actually crosses.
+/// That's O((n + k) log n) for n lines and k crossings.
+/// Lines along the same row or column are checked for overlaps
+/// separately.
+pub fn crossings(wires: &[Vec<Line>]) -> Vec<Crossing> {
+    let mut segments: Vec<Segment> = Vec::new();
+    for (wire, lines) in wires.iter().enumerate() {
+        let mut steps = 0;
+        for line in lines {
+            segments.push(Segment { wire, line, steps });
+            steps += line.blocks();
+        }
+    }
+    let mut found = HashMap::new();
+
+    // Events are (x, kind, segment) where kind orders what happens
+    // at the same x: add horizontals, check verticals, drop horizontals
+    const ADD: u8 = 0;
+    const CHECK: u8 = 1;
+    const DROP: u8 = 2;
+    let mut events: Vec<(isize, u8, usize)> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let line = segment.line;
+        match line.orientation {
+            Orientation::Horizontal => {
+                events.push((min(line.x1, line.x2), ADD, i));
+                events.push((max(line.x1, line.x2), DROP, i));
+            },
+            Orientation::Vertical => {
+                events.push((line.x1, CHECK, i));
+            },
+        }
+    }
+    events.sort_unstable();
+
+    // Horizontals under the sweep line as (y, segment)
+    let mut active: BTreeSet<(isize, usize)> = BTreeSet::new();
+    for (x, kind, i) in events {
+        let line = segments[i].line;
+        match kind {
+            ADD => { active.insert((line.y1, i)); },
+            DROP => { active.remove(&(line.y1, i)); },
+            _ => {
+                let (lo, hi) = line.span();
+                for (y, j) in active.range((lo, 0)..=(hi, usize::MAX)) {
+                    record(&mut found, (x, *y), &segments[i], &segments[*j]);
+                }
+            },
+        }
+    }
+
+    // Overlaps between lines on the same row or column: sort each
+    // row/column by where the lines start and keep the ones still going
+    let mut collinear: HashMap<(bool, isize), Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let line = segment.line;
+        collinear
+            .entry((line.orientation == Orientation::Horizontal, line.along()))
+            .or_default()
+            .push(i);
+    }
+    for mut group in collinear.into_values() {
+        group.sort_unstable_by_key(|i| segments[*i].line.span());
+        let mut going: Vec<usize> = Vec::new();
+        for i in group {
+            let (lo, _) = segments[i].line.span();
+            going.retain(|j| segments[*j].line.span().1 >= lo);
+            for j in going.iter() {
+                if segments[i].wire == segments[*j].wire {
+                    continue;
+                }
+                for at in segments[i].line.intercept_points(segments[*j].line) {
+                    record(&mut found, at, &segments[i], &segments[*j]);
+                }
             }
+            going.push(i);
         }
     }
+
+    let mut crossings = found
+        .into_iter()
+        .map(|((at, a, b), steps)| Crossing { at, wires: (a, b), steps })
+        .collect::<Vec<Crossing>>();
+    crossings.sort_unstable_by_key(|c| (c.wires, c.at));
+    crossings
+}
+
+/// The crossing closest to the origin by Manhattan distance.
+pub fn closest(crossings: &[Crossing]) -> Option<&Crossing> {
+    crossings
+        .iter()
+        .min_by_key(|c| c.at.0.abs() + c.at.1.abs())
+}
+
+/// The crossing reached in the fewest combined steps along its wires.
+pub fn fewest_steps(crossings: &[Crossing]) -> Option<&Crossing> {
     crossings
+        .iter()
+        .min_by_key(|c| c.steps.0 + c.steps.1)
 }
 
 
+#[test]
+fn test_crossings_match_pairwise() {
+    // Pseudo-random wires, checked against comparing every pair of lines
+    let mut seed = 12345u64;
+    let mut wires = Vec::new();
+    for _ in 0..3 {
+        let moves = (0..300)
+            .map(|i| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let value = (seed >> 33) as isize % 50 + 1;
+                let value = if (seed >> 20) & 1 == 0 { value } else { -value };
+                if i % 2 == 0 { (value, 0) } else { (0, value) }
+            })
+            .collect::<Vec<Coords>>();
+        wires.push(moves_to_lines(&moves));
+    }
+
+    let mut expected = Vec::new();
+    for a in 0..wires.len() {
+        for b in (a + 1)..wires.len() {
+            for line_a in wires[a].iter() {
+                for line_b in wires[b].iter() {
+                    for at in line_a.intercept_points(line_b) {
+                        if at != ORIGIN {
+                            expected.push((at, a, b));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let mut actual = crossings(&wires)
+        .into_iter()
+        .map(|c| (c.at, c.wires.0, c.wires.1))
+        .collect::<Vec<_>>();
+    expected.sort();
+    expected.dedup();
+    actual.sort();
+    assert!(!actual.is_empty());
+    assert_eq!(actual, expected);
+}
+
+
+#[cfg(test)]
 fn steps_in(lines: &[Line]) -> usize {
     lines
         .iter()
@@ -196,45 +432,166 @@ fn test_steps_in() {
 }
 
 
+#[test]
+fn test_examples() {
+    let tests = [
+        ("example0", 6, 30),
+        ("example1", 159, 610),
+        ("example2", 135, 410),
+    ];
+    for (file, distance, steps) in tests.iter() {
+        let source = data_file_to_string("3", file).unwrap();
+        let lines = rows_of_lines(&rows_of_moves(source));
+        let crossings = crossings(&lines);
+        let closest = closest(&crossings).unwrap();
+        assert_eq!(closest.at.0.abs() + closest.at.1.abs(), *distance);
+        let fewest = fewest_steps(&crossings).unwrap();
+        assert_eq!(fewest.steps.0 + fewest.steps.1, *steps);
+    }
+}
+
+
+#[test]
+fn test_crossings_loops_and_overlaps() {
+    // The first wire loops back over (5,0), and the second runs
+    // along the first's last line for a bit
+    let lines = rows_of_lines(&rows_of_moves(String::from("R10,U5,L5,D10\nD2,R5,U2")));
+    let actual = crossings(&lines)
+        .into_iter()
+        .map(|c| (c.at, c.steps))
+        .collect::<Vec<_>>();
+    assert_eq!(actual, vec![((5,0), (5,9)), ((5,1), (26,8)), ((5,2), (27,7))]);
+
+    // A zero-length move doesn't add another crossing
+    let lines = rows_of_lines(&rows_of_moves(String::from("R5,U0,R5\nD1,R5,U2")));
+    let actual = crossings(&lines);
+    assert_eq!(actual, vec![Crossing { at: (5,0), wires: (0,1), steps: (5,7) }]);
+
+    // Wires that only meet at the origin don't cross
+    let lines = rows_of_lines(&rows_of_moves(String::from("R5\nU5")));
+    assert!(crossings(&lines).is_empty());
+}
+
+
+/// Colours for the wires, reused if there are lots of wires.
+const WIRE_COLOURS: [&str; 6] =
+    ["#1f77b4", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2"];
+/// Width of the rendered picture in pixels.
+const SVG_WIDTH: isize = 800;
+
+/// Draw the wires as an SVG picture, marking the origin, every crossing
+/// and the closest and fewest-steps crossings. Hovering over a crossing
+/// shows its position and the steps along each wire.
+pub fn svg(wires: &[Vec<Line>], crossings: &[Crossing]) -> String {
+    let points = wires
+        .iter()
+        .flatten()
+        .flat_map(|l| [(l.x1, l.y1), (l.x2, l.y2)])
+        .chain(std::iter::once(ORIGIN));
+    let (mut x_min, mut y_min, mut x_max, mut y_max) = (0, 0, 0, 0);
+    for (x, y) in points {
+        x_min = min(x, x_min);
+        y_min = min(y, y_min);
+        x_max = max(x, x_max);
+        y_max = max(y, y_max);
+    }
+    // Leave a margin, and size the markers to suit the picture
+    let extent = max(max(x_max - x_min, y_max - y_min), 1);
+    let margin = max(extent / 20, 1);
+    let (x_min, y_min) = (x_min - margin, y_min - margin);
+    let (width, height) = (x_max - x_min + margin, y_max - y_min + margin);
+    let radius = extent as f32 / 150.0;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n",
+        SVG_WIDTH, SVG_WIDTH * height / width, x_min, y_min, width, height);
+    svg.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+        x_min, y_min, width, height));
+
+    for (i, wire) in wires.iter().enumerate() {
+        let mut points = vec![format!("{},{}", ORIGIN.0, ORIGIN.1)];
+        points.extend(wire.iter().map(|l| format!("{},{}", l.x2, l.y2)));
+        svg.push_str(&format!(
+            "<polyline class=\"wire\" points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" vector-effect=\"non-scaling-stroke\"/>\n",
+            points.join(" "), WIRE_COLOURS[i % WIRE_COLOURS.len()]));
+    }
+
+    let circle = |class: &str, at: Coords, r: f32, style: &str, title: String| {
+        format!(
+            "<circle class=\"{}\" cx=\"{}\" cy=\"{}\" r=\"{}\" {}><title>{}</title></circle>\n",
+            class, at.0, at.1, r, style, title)
+    };
+    let describe = |c: &Crossing| format!(
+        "({}, {}) distance {} steps {} + {} = {}",
+        c.at.0, c.at.1, c.at.0.abs() + c.at.1.abs(),
+        c.steps.0, c.steps.1, c.steps.0 + c.steps.1);
+    svg.push_str(&circle("origin", ORIGIN, radius * 1.5,
+                         "fill=\"black\"", String::from("origin")));
+    for crossing in crossings {
+        svg.push_str(&circle("crossing", crossing.at, radius,
+                             "fill=\"grey\"", describe(crossing)));
+    }
+    let ring = |colour: &str|
+        format!("fill=\"none\" stroke=\"{}\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\"", colour);
+    if let Some(c) = closest(crossings) {
+        svg.push_str(&circle("closest", c.at, radius * 2.5, &ring("red"),
+                             format!("closest: {}", describe(c))));
+    }
+    if let Some(c) = fewest_steps(crossings) {
+        svg.push_str(&circle("fewest-steps", c.at, radius * 3.5, &ring("blue"),
+                             format!("fewest steps: {}", describe(c))));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+
+#[test]
+fn test_svg() {
+    let source = data_file_to_string("3", "example0").unwrap();
+    let lines = rows_of_lines(&rows_of_moves(source));
+    let crossings = crossings(&lines);
+    let svg = svg(&lines, &crossings);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(svg.contains("viewBox=\"-1 -8 10 9\""));
+    assert!(svg.contains("points=\"0,0 8,0 8,-5 3,-5 3,-2\""));
+    assert!(svg.contains("points=\"0,0 0,-7 6,-7 6,-3 2,-3\""));
+    assert_eq!(svg.matches("class=\"wire\"").count(), 2);
+    assert_eq!(svg.matches("class=\"origin\"").count(), 1);
+    assert_eq!(svg.matches("class=\"crossing\"").count(), 2);
+    assert!(svg.contains("<title>closest: (3, -3) distance 6 steps 20 + 20 = 40</title>"));
+    assert!(svg.contains("<title>fewest steps: (6, -5) distance 11 steps 15 + 15 = 30</title>"));
+}
+
+
+/// Print an SVG picture of the wires, e.g. to redirect into a file.
+pub fn draw() {
+    let source = data_file_to_string("3", "input").unwrap();
+    let lines = rows_of_lines(&rows_of_moves(source));
+    let crossings = crossings(&lines);
+    print!("{}", svg(&lines, &crossings));
+}
+
+
 pub fn part1() {
     let source = data_file_to_string("3", "input").unwrap();
-    let moves = rows_of_moves(source);
-    let lines = rows_of_lines(&moves);
-    let crossings = crossings(&lines[0], &lines[1]);
-    let mut answer = &crossings[1].0;
-    for (crossing, _, _) in crossings.iter().skip(1) {
-        println!("{:?}", crossing);
-        if (crossing.0.abs() + crossing.1.abs()) < (answer.0.abs() + answer.1.abs()) {
-            answer = &crossing;
-        }
+    let lines = rows_of_lines(&rows_of_moves(source));
+    let crossings = crossings(&lines);
+    match closest(&crossings) {
+        Some(crossing) => println!("{}", crossing.at.0.abs() + crossing.at.1.abs()),
+        None => println!("The wires don't cross"),
     }
-    println!("{}", answer.0.abs() + answer.1.abs());
 }
 
 
 pub fn part2() {
     let source = data_file_to_string("3", "input").unwrap();
-    let moves = rows_of_moves(source);
-    let lines = rows_of_lines(&moves);
-    let crossings = crossings(&lines[0], &lines[1]);
-    let mut answer = 1_000_000;
-    for (crossing, i_a, i_b) in crossings.iter() {
-        let mut dist = steps_in(&lines[0][..*i_a]) + steps_in(&lines[1][..*i_b]);
-        // account for final coord up till intersection point
-        if *i_a > 0 {
-            dist += Line::new(lines[0][*i_a - 1].x2, lines[0][*i_a - 1].y2, crossing.0, crossing.1).blocks();
-        }
-        else {
-            dist += Line::new(0, 0, crossing.0, crossing.1).blocks();
-        }
-        if *i_b > 0 {
-            dist += Line::new(lines[1][*i_b - 1].x2, lines[1][*i_b - 1].y2, crossing.0, crossing.1).blocks();
-        }
-        else {
-            dist += Line::new(0, 0, crossing.0, crossing.1).blocks();
-        }
-        println!("{:?} @ {}, {} dist {}", crossing, *i_a, *i_b, dist);
-        answer = min(dist, answer);
+    let lines = rows_of_lines(&rows_of_moves(source));
+    let crossings = crossings(&lines);
+    match fewest_steps(&crossings) {
+        Some(crossing) => println!("{}", crossing.steps.0 + crossing.steps.1),
+        None => println!("The wires don't cross"),
     }
-    println!("{}", answer);
 }
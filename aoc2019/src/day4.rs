@@ -1,16 +1,68 @@
 use std::iter::Iterator;
 
-const FIRST:usize = 235741;
-const LAST:usize = 706948;
+use crate::common::*;
 
-struct PwSearch {
+/// A check on the digits of a password candidate.
+pub type Rule = Box<dyn Fn(&[u8]) -> bool>;
+
+/// Some two adjacent digits are the same (part 1).
+pub fn has_pair(digits: &[u8]) -> bool {
+    digits.windows(2).any(|w| w[0] == w[1])
+}
+
+/// Some run of adjacent matching digits is exactly two long (part 2).
+pub fn has_exact_pair(digits: &[u8]) -> bool {
+    digits
+        .chunk_by(|a, b| a == b)
+        .any(|run| run.len() == 2)
+}
+
+/// Digits never go down from left to right.
+fn non_decreasing(digits: &[u8]) -> bool {
+    digits.windows(2).all(|w| w[0] <= w[1])
+}
+
+/// Is this a valid password by the part 1 rules?
+pub fn valid_part1(password: usize) -> bool {
+    let digits = PwSearch::digits(password);
+    non_decreasing(&digits) && has_pair(&digits)
+}
+
+/// Is this a valid password by the part 2 rules?
+pub fn valid_part2(password: usize) -> bool {
+    let digits = PwSearch::digits(password);
+    non_decreasing(&digits) && has_exact_pair(&digits)
+}
+
+/// Searches a range for passwords whose digits never decrease and
+/// which pass all the rules.
+/// Only non-decreasing digit sequences get generated, so this skips
+/// over most of the range rather than checking every number in it.
+pub struct PwSearch {
     last   : usize,
-    current: usize,
+    // Digits of the next candidate, or None when done
+    current: Option<Vec<u8>>,
+    rules  : Vec<Rule>,
 }
 
 impl PwSearch {
-    fn new(first: usize, last: usize) -> Self {
-        PwSearch {last, current: first, }
+    pub fn new(first: usize, last: usize) -> Self {
+        let mut current = Self::digits(first);
+        // Jump to the first non-decreasing sequence at or after `first`
+        for i in 1..current.len() {
+            if current[i] < current[i - 1] {
+                let fill = current[i - 1];
+                current[i..].iter_mut().for_each(|d| *d = fill);
+                break;
+            }
+        }
+        PwSearch {last, current: Some(current), rules: Vec::new() }
+    }
+
+    /// Add a rule that passwords must pass.
+    pub fn rule(mut self, rule: impl Fn(&[u8]) -> bool + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
     }
 
     fn digits(num: usize) -> Vec<u8> {
@@ -21,44 +73,53 @@ impl PwSearch {
             .collect::<Vec<u8>>()
     }
 
-    fn check_digits(digits: Vec<u8>) -> bool {
-        let mut two_adj = false;
-        let mut count_adj = 1u8;
-        let mut prev = digits[0];
-        for digit in digits.into_iter().skip(1) {
-            if digit == prev {
-                count_adj += 1;
-            }
-            else {
-                two_adj = two_adj || count_adj == 2;
-                count_adj = 1;
-            }
-            if prev > digit {
-                return false;
-            }
-            prev = digit;
+    fn number(digits: &[u8]) -> usize {
+        digits.iter().fold(0, |acc, d| acc * 10 + *d as usize)
+    }
+
+    /// Step to the next non-decreasing sequence: bump the rightmost
+    /// digit that isn't a 9 and fill everything after it with the same
+    /// digit, or go up to the next length (all 1s) after all 9s.
+    fn advance(digits: &mut Vec<u8>) {
+        match digits.iter().rposition(|d| *d < 9) {
+            Some(i) => {
+                let fill = digits[i] + 1;
+                digits[i..].iter_mut().for_each(|d| *d = fill);
+            },
+            None => {
+                *digits = vec![1; digits.len() + 1];
+            },
         }
-        two_adj || count_adj == 2
     }
 
-    fn check_usize(num: usize) -> bool {
-        Self::check_digits(Self::digits(num))
+    fn check_digits(&self, digits: &[u8]) -> bool {
+        non_decreasing(digits)
+            && self.rules.iter().all(|rule| rule(digits))
+    }
+
+    #[cfg(test)]
+    fn check_usize(&self, num: usize) -> bool {
+        self.check_digits(&Self::digits(num))
     }
 }
 
 #[test]
 fn test_check_usize() {
     let tests = [
-        (111111, false),
-        (112233, true),
-        (123444, false),
-        (123789, false),
-        (111122, true),
+        (111111, true, false),
+        (112233, true, true),
+        (123444, true, false),
+        (123789, false, false),
+        (111122, true, true),
+        (223450, false, false),
     ];
+    let part1 = PwSearch::new(0, 0).rule(has_pair);
+    let part2 = PwSearch::new(0, 0).rule(has_exact_pair);
     for test in &tests {
-        let rez = PwSearch::check_usize(test.0);
+        let rez = (part1.check_usize(test.0), part2.check_usize(test.0));
         println!("{:?} => {:?}", test, rez);
-        assert_eq!(rez, test.1);
+        assert_eq!(rez, (test.1, test.2));
+        assert_eq!((valid_part1(test.0), valid_part2(test.0)), (test.1, test.2));
     }
 }
 
@@ -66,9 +127,15 @@ impl Iterator for PwSearch {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        'search: for num in self.current..=self.last {
-            if Self::check_usize(num) {
-                self.current = num + 1;
+        while let Some(digits) = self.current.as_mut() {
+            let num = Self::number(digits);
+            if num > self.last {
+                self.current = None;
+                break;
+            }
+            let found = digits.clone();
+            Self::advance(digits);
+            if self.check_digits(&found) {
                 return Some(num);
             }
         }
@@ -76,9 +143,72 @@ impl Iterator for PwSearch {
     }
 }
 
-pub fn part1() {
-    // Could filter the first and last values before searching,
-    // but ain't nobody got time for that
-    let p = PwSearch::new(FIRST, LAST).count();
-    println!("{}", p);
+#[test]
+fn test_search_matches_brute_force() {
+    let brute = |first: usize, last: usize, rule: fn(&[u8]) -> bool| {
+        (first..=last)
+            .filter(|n| {
+                let digits = PwSearch::digits(*n);
+                non_decreasing(&digits) && rule(&digits)
+            })
+            .collect::<Vec<usize>>()
+    };
+    // Includes ranges that cross into more digits
+    for (first, last) in [(235741, 306948), (0, 2000), (95, 1234), (9999, 11223)] {
+        for rule in [has_pair, has_exact_pair] {
+            let found = PwSearch::new(first, last).rule(rule).collect::<Vec<usize>>();
+            assert_eq!(found, brute(first, last, rule));
+        }
+    }
+    // Custom rules stack up with the others
+    let found = PwSearch::new(100, 200)
+        .rule(has_pair)
+        .rule(|digits| digits.contains(&5))
+        .collect::<Vec<usize>>();
+    assert_eq!(found, vec![115, 155]);
+}
+
+/// Read the range to search from `lo-hi` input.
+pub fn parse_range(source: &str) -> Option<(usize, usize)> {
+    let (lo, hi) = source.trim().split_once('-')?;
+    let lo = lo.trim().parse::<usize>().ok()?;
+    let hi = hi.trim().parse::<usize>().ok()?;
+    Some((lo, hi))
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range("235741-706948\n"), Some((235741, 706948)));
+    assert_eq!(parse_range(" 1 - 20 "), Some((1, 20)));
+    assert_eq!(parse_range("235741"), None);
+    assert_eq!(parse_range("a-b"), None);
+}
+
+/// Count the passwords in a range for both parts.
+/// Part 1 allows any pair, part 2 needs one that isn't part of a
+/// bigger group; both come from the same search.
+pub fn solve(first: usize, last: usize) -> (usize, usize) {
+    (
+        PwSearch::new(first, last).rule(has_pair).count(),
+        PwSearch::new(first, last).rule(has_exact_pair).count(),
+    )
+}
+
+#[test]
+fn test_solve() {
+    let source = data_file_to_string("4", "input").unwrap();
+    let (first, last) = parse_range(&source).unwrap();
+    assert_eq!(solve(first, last), (1178, 763));
+}
+
+pub fn run() -> (usize, usize) {
+    let source = data_file_to_string("4", "input").unwrap();
+    let (first, last) = match parse_range(&source) {
+        Some(range) => range,
+        None => panic!("Expected a range like 123456-654321, got {:?}", source.trim()),
+    };
+    let answers = solve(first, last);
+    println!("{}", answers.0);
+    println!("{}", answers.1);
+    answers
 }
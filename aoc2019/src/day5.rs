@@ -1,19 +1,67 @@
 use crate::common::*;
-use crate::intcode::Intcode;
+use crate::intcode::{Intcode,Word,parse_program};
 
-pub fn run() {
-    let original = 
-        data_file_to_string("5", "input").unwrap()
-            .split(',')
-            .map(|i| i.trim())
-            .filter(|i| i.len() > 0)
-            .map(|i| i.parse::<isize>().unwrap())
-            .collect::<Vec<isize>>();
-    let mut tape: Vec<isize>;
-    let result: isize;
+/// Ship's air conditioner unit
+const AIR_CONDITIONER: Word = 1;
+/// Ship's thermal radiator controller
+const THERMAL_RADIATOR: Word = 5;
+
+/// Why a diagnostic run didn't give a code.
+#[derive(Debug,PartialEq)]
+pub enum DiagnosticError {
+    /// The program didn't output anything
+    NoOutput,
+    /// A test (numbered from 1) output something other than zero
+    TestFailed { test: usize, output: Word },
+}
+
+/// Run the TEST diagnostic program for a system, getting all outputs.
+pub fn run_diagnostic(program: &[Word], system_id: Word) -> Vec<Word> {
+    let mut runner = Intcode::new(program.to_vec());
+    runner.run_with_inputs(&[system_id])
+}
+
+/// Every output but the last is a test that should give zero;
+/// the last is the diagnostic code.
+pub fn check(outputs: &[Word]) -> Result<Word, DiagnosticError> {
+    let (code, tests) = match outputs.split_last() {
+        Some(split) => split,
+        None => return Err(DiagnosticError::NoOutput),
+    };
+    match tests.iter().position(|t| *t != 0) {
+        Some(i) => Err(DiagnosticError::TestFailed { test: i + 1, output: tests[i] }),
+        None => Ok(*code),
+    }
+}
+
+#[test]
+fn test_check() {
+    assert_eq!(check(&[0, 0, 0, 1234]), Ok(1234));
+    assert_eq!(check(&[1234]), Ok(1234));
+    assert_eq!(check(&[]), Err(DiagnosticError::NoOutput));
+    assert_eq!(check(&[0, 3, 0, 5, 1234]),
+               Err(DiagnosticError::TestFailed { test: 2, output: 3 }));
+}
 
-    tape = original.clone();
-    let mut runner = Intcode::new(&mut tape);
-    result = runner.run(5);
-    println!("{}", result);
+#[test]
+fn test_run_diagnostic() {
+    let program = parse_program(&data_file_to_string("5", "input").unwrap());
+    let outputs = run_diagnostic(&program, AIR_CONDITIONER);
+    assert!(outputs.len() > 1);
+    assert!(check(&outputs).is_ok());
+    let outputs = run_diagnostic(&program, THERMAL_RADIATOR);
+    assert_eq!(outputs.len(), 1);
+    assert!(check(&outputs).is_ok());
+}
+
+pub fn run() {
+    let original = parse_program(&data_file_to_string("5", "input").unwrap());
+    for system_id in [AIR_CONDITIONER, THERMAL_RADIATOR] {
+        let outputs = run_diagnostic(&original, system_id);
+        println!("System {} outputs {:?}", system_id, outputs);
+        match check(&outputs) {
+            Ok(code) => println!("{}", code),
+            Err(e) => println!("Diagnostic failed: {:?}", e),
+        }
+    }
 }
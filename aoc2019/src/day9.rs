@@ -0,0 +1,52 @@
+use crate::common::*;
+use crate::intcode::{Intcode,Word,parse_program};
+
+/// BOOST checks every opcode and parameter mode
+const TEST_MODE: Word = 1;
+/// BOOST works out the distress signal's coordinates
+const SENSOR_BOOST: Word = 2;
+
+/// Run BOOST with an input, getting everything it outputs.
+pub fn boost(program: &[Word], input: Word) -> Vec<Word> {
+    let mut runner = Intcode::new(program.to_vec());
+    runner.run_with_inputs(&[input])
+}
+
+/// Run BOOST in test mode. If everything works it outputs just the
+/// BOOST keycode, otherwise it outputs the opcodes that failed.
+pub fn keycode(program: &[Word]) -> Result<Word, Vec<Word>> {
+    match boost(program, TEST_MODE).as_slice() {
+        [keycode] => Ok(*keycode),
+        failures => Err(failures.to_vec()),
+    }
+}
+
+/// Run BOOST in sensor boost mode to get the coordinates.
+pub fn coordinates(program: &[Word]) -> Option<Word> {
+    boost(program, SENSOR_BOOST).last().copied()
+}
+
+#[test]
+fn test_keycode() {
+    // Echoes the input, as if it all worked
+    assert_eq!(keycode(&[3,0,4,0,99]), Ok(TEST_MODE));
+    // Complains about a couple of opcodes
+    assert_eq!(keycode(&[104,203,104,209,99]), Err(vec![203, 209]));
+    assert_eq!(keycode(&[99]), Err(vec![]));
+    // Works with relative mode and numbers too big for 32 bits
+    assert_eq!(coordinates(&[99]), None);
+    assert_eq!(coordinates(&[109,11,203,0,21202,0,10_000_000_000,1,204,1,99]),
+               Some(20_000_000_000));
+}
+
+pub fn run() {
+    let program = parse_program(&data_file_to_string("9", "input").unwrap());
+    match keycode(&program) {
+        Ok(keycode) => println!("{}", keycode),
+        Err(failures) => println!("Opcodes failed: {:?}", failures),
+    }
+    match coordinates(&program) {
+        Some(coordinates) => println!("{}", coordinates),
+        None => println!("No coordinates"),
+    }
+}
@@ -1,58 +1,174 @@
-pub struct Intcode<'a> {
-    tape: &'a mut Vec<isize>,
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+/// Intcode values are 64-bit whatever the target.
+pub type Word = i64;
+
+/// Why the machine stopped running.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Event {
+    /// It output a value; resume to carry on
+    Output(Word),
+    /// It wants input that hasn't been given yet
+    NeedInput,
+    /// It hit the EXIT code
+    Halted,
+}
+
+/// Read a comma-separated Intcode program.
+pub fn parse_program(source: &str) -> Vec<Word> {
+    source
+        .split(',')
+        .map(|i| i.trim())
+        .filter(|i| !i.is_empty())
+        .map(|i| i.parse::<Word>().unwrap())
+        .collect::<Vec<Word>>()
+}
+
+/// An Intcode machine that owns its memory, so it can be paused
+/// while waiting for input and cloned to try things out.
+#[derive(Debug,Clone)]
+pub struct Intcode {
+    tape: Vec<Word>,
     pointer: usize,
+    relative_base: Word,
+    inputs: VecDeque<Word>,
 }
 
-impl<'a> Intcode<'a> {
-    pub fn new(tape: &'a mut Vec<isize>) -> Self {
-        Intcode { tape, pointer: 0 }
+impl Intcode {
+    pub fn new(tape: Vec<Word>) -> Self {
+        Intcode { tape, pointer: 0, relative_base: 0, inputs: VecDeque::new() }
     }
 
-    fn read(&mut self, mode: isize) -> isize {
-        let result = 
+    /// The machine's memory.
+    pub fn tape(&self) -> &[Word] {
+        &self.tape
+    }
+
+    /// Queue up a value for the program to read.
+    pub fn input(&mut self, value: Word) {
+        self.inputs.push_back(value);
+    }
+
+    /// Queue up a line of ASCII text, adding the newline.
+    pub fn input_line(&mut self, line: &str) {
+        self.inputs.extend(line.bytes().map(Word::from));
+        self.input(Word::from(b'\n'));
+    }
+
+    /// Run until the program wants input it hasn't got or exits,
+    /// collecting its ASCII output as text. Anything outside ASCII
+    /// comes back separately.
+    pub fn read_ascii(&mut self) -> (String, Vec<Word>) {
+        let mut text = String::new();
+        let mut others = Vec::new();
+        while let Event::Output(value) = self.resume() {
+            match u8::try_from(value) {
+                Ok(byte) if byte.is_ascii() => text.push(char::from(byte)),
+                _ => others.push(value),
+            }
+        }
+        (text, others)
+    }
+
+    /// Get the address a parameter refers to, and move past it.
+    fn address(&mut self, mode: Word) -> usize {
+        let param = self.get(self.pointer);
+        let address =
             match mode % 10 {
-                0 => {
-                    self.tape[self.tape[self.pointer] as usize]
-                },
-                1 => {
-                    self.tape[self.pointer]
-                },
+                0 => param,                       // position
+                1 => self.pointer as Word,        // immediate
+                2 => self.relative_base + param,  // relative
                 _ => panic!("Unknown mode {}", mode),
             };
         self.pointer += 1;
-        result
+        if address < 0 {
+            panic!("Negative address {}", address);
+        }
+        address as usize
     }
 
-    pub fn run(&'a mut self, mut input: isize) -> isize {
+    /// Memory past the end of the program starts as zero.
+    fn get(&self, address: usize) -> Word {
+        self.tape.get(address).copied().unwrap_or(0)
+    }
+
+    fn read(&mut self, mode: Word) -> Word {
+        let address = self.address(mode);
+        self.get(address)
+    }
+
+    fn write(&mut self, mode: Word, value: Word) {
+        let address = self.address(mode);
+        if address >= self.tape.len() {
+            self.tape.resize(address + 1, 0);
+        }
+        self.tape[address] = value;
+    }
+
+    /// Run with a single input, giving back the last output
+    /// (or the input if nothing was output).
+    pub fn run(&mut self, input: Word) -> Word {
+        self.run_with_inputs(&[input])
+            .last()
+            .copied()
+            .unwrap_or(input)
+    }
+
+    /// Run until the program exits, feeding it inputs in order and
+    /// collecting everything it outputs.
+    pub fn run_with_inputs(&mut self, inputs: &[Word]) -> Vec<Word> {
+        self.inputs.extend(inputs);
+        let mut outputs = Vec::new();
+        loop {
+            match self.resume() {
+                Event::Output(value) => outputs.push(value),
+                Event::NeedInput => panic!("Ran out of input"),
+                Event::Halted => break,
+            }
+        }
+        outputs
+    }
+
+    /// Run until the program outputs something, needs input it
+    /// hasn't got, or exits.
+    pub fn resume(&mut self) -> Event {
         loop {
+            let start = self.pointer;
             let opcode = self.read(1);
             let mode = opcode / 100;
             let opcode = opcode % 100;
 
-            // EXIT code
-            if opcode == 99 { break; }
+            // EXIT code; stay put so resuming again still says so
+            if opcode == 99 {
+                self.pointer = start;
+                return Event::Halted;
+            }
             
             match opcode {
                 1 => {  // ADD
                     let operand_1 = self.read(mode);
                     let operand_2 = self.read(mode / 10);
-                    let result_pos = self.read(1);
-                    let value = operand_1 + operand_2;
-                    self.tape[result_pos as usize] = value;
+                    self.write(mode / 100, operand_1 + operand_2);
                 },
                 2 => {  // MUL
                     let operand_1 = self.read(mode);
                     let operand_2 = self.read(mode / 10);
-                    let result_pos = self.read(1);
-                    let value = operand_1 * operand_2;
-                    self.tape[result_pos as usize] = value;
+                    self.write(mode / 100, operand_1 * operand_2);
                 },
                 3 => {  // STORE
-                    let result_pos = self.read(1);
-                    self.tape[result_pos as usize] = input;
+                    let input = match self.inputs.pop_front() {
+                        Some(input) => input,
+                        None => {
+                            // Wait here until there's some input
+                            self.pointer = start;
+                            return Event::NeedInput;
+                        },
+                    };
+                    self.write(mode, input);
                 },
                 4 => { // RETRIEVE
-                    input = self.read(mode);
+                    return Event::Output(self.read(mode));
                 },
                 5 => {  // JNZ
                     let operand_1 = self.read(mode);
@@ -71,67 +187,119 @@ impl<'a> Intcode<'a> {
                 7 => {  // LT
                     let operand_1 = self.read(mode);
                     let operand_2 = self.read(mode / 10);
-                    let result_pos = self.read(1);
                     let value = if operand_1 < operand_2 { 1 } else { 0 };
-                    self.tape[result_pos as usize] = value;
+                    self.write(mode / 100, value);
                 },
                 8 => {  // EQ
                     let operand_1 = self.read(mode);
                     let operand_2 = self.read(mode / 10);
-                    let result_pos = self.read(1);
                     let value = if operand_1 == operand_2 { 1 } else { 0 };
-                    self.tape[result_pos as usize] = value;
+                    self.write(mode / 100, value);
                 },
-                99 => {
-                    break;
+                9 => {  // RELBASE
+                    self.relative_base += self.read(mode);
                 },
                 _ => {
                     panic!("Unknown opcode {}", opcode);
                 }
             }
         }
-        input
     }
 }
 
+#[test]
+fn test_resume() {
+    // Double each input until given zero
+    let mut ic = Intcode::new(vec![3,15, 1006,15,14, 1002,15,2,16, 4,16, 1105,1,0, 99]);
+    assert_eq!(ic.resume(), Event::NeedInput);
+    assert_eq!(ic.resume(), Event::NeedInput);
+    ic.input(21);
+    ic.input(5);
+    assert_eq!(ic.resume(), Event::Output(42));
+    assert_eq!(ic.resume(), Event::Output(10));
+    assert_eq!(ic.resume(), Event::NeedInput);
+
+    // Clones carry on separately
+    let mut other = ic.clone();
+    other.input(0);
+    assert_eq!(other.resume(), Event::Halted);
+    assert_eq!(other.resume(), Event::Halted);
+    ic.input(1);
+    assert_eq!(ic.resume(), Event::Output(2));
+}
+
+#[test]
+fn test_ascii() {
+    // Says hi, outputs a big number, then echoes a character
+    let mut ic = Intcode::new(vec![104,72, 104,105, 104,10, 104,1000, 3,50, 4,50, 99]);
+    assert_eq!(ic.read_ascii(), (String::from("Hi\n"), vec![1000]));
+    assert_eq!(ic.resume(), Event::NeedInput);
+    ic.input_line("Z");
+    assert_eq!(ic.read_ascii(), (String::from("Z"), vec![]));
+    assert_eq!(ic.resume(), Event::Halted);
+}
+
+#[test]
+fn test_relative_mode() {
+    // Outputs a copy of itself, reading past the end of the program
+    let quine: Vec<Word> =
+        vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+    let mut ic = Intcode::new(quine.clone());
+    assert_eq!(ic.run_with_inputs(&[]), quine);
+
+    // Big numbers
+    let mut ic = Intcode::new(vec![1102,34915192,34915192,7,4,7,99,0]);
+    assert_eq!(ic.run(0), 1219070632396864);
+    let mut ic = Intcode::new(vec![104,1125899906842624,99]);
+    assert_eq!(ic.run(0), 1125899906842624);
+
+    // Input and output in relative mode
+    let mut ic = Intcode::new(vec![109,10,203,0,204,0,99]);
+    assert_eq!(ic.run(-42), -42);
+    assert_eq!(ic.tape()[10], -42);
+}
+
+#[test]
+fn test_run_with_inputs() {
+    // Add two inputs, output both and the sum
+    let mut ic = Intcode::new(vec![3,20, 3,21, 1,20,21,22, 4,20, 4,21, 4,22, 99]);
+    assert_eq!(ic.run_with_inputs(&[3, 4]), vec![3, 4, 7]);
+}
+
 #[test]
 fn test_run() {
-    let example: Vec<isize> = vec![3,9,8,9,10,9,4,9,99,-1,8];
+    let example: Vec<Word> = vec![3,9,8,9,10,9,4,9,99,-1,8];
     let tests = [(7, 0), (8, 1), (9, 0), (256, 0)];
     println!("Equal to eight?");
     for (test, expected) in tests.iter() {
-        let mut tape = example.clone();
-        let mut ic = Intcode::new(&mut tape);
+        let mut ic = Intcode::new(example.clone());
         let actual = ic.run(*test);
         assert_eq!(*expected, actual);
     }
 
-    let example: Vec<isize> = vec![3,3,1108,-1,8,3,4,3,99];
+    let example: Vec<Word> = vec![3,3,1108,-1,8,3,4,3,99];
     let tests = [(7, 0), (8, 1), (9, 0), (256, 0)];
     println!("Equal to eight?");
     for (test, expected) in tests.iter() {
-        let mut tape = example.clone();
-        let mut ic = Intcode::new(&mut tape);
+        let mut ic = Intcode::new(example.clone());
         let actual = ic.run(*test);
         assert_eq!(*expected, actual);
     }
 
-    let example: Vec<isize> = vec![3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9];
+    let example: Vec<Word> = vec![3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9];
     let tests = [(0, 0), (8, 1), (9, 1), (-256, 1)];
     println!("Zero or non-zero?");
     for (test, expected) in tests.iter() {
-        let mut tape = example.clone();
-        let mut ic = Intcode::new(&mut tape);
+        let mut ic = Intcode::new(example.clone());
         let actual = ic.run(*test);
         assert_eq!(*expected, actual);
     }
 
-    let example:Vec<isize> = vec![3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+    let example:Vec<Word> = vec![3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
     let tests = [(7, 999), (8, 1000), (9, 1001), (256, 1001), (-256, 999)];
     println!("Relative to eight?");
     for (test, expected) in tests.iter() {
-        let mut tape = example.clone();
-        let mut ic = Intcode::new(&mut tape);
+        let mut ic = Intcode::new(example.clone());
         let actual = ic.run(*test);
         assert_eq!(*expected, actual);
     }